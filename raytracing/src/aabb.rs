@@ -0,0 +1,177 @@
+//! Defines a type for axis-aligned bounding boxes.
+
+use crate::ray::Ray;
+use crate::transform::Transform;
+use crate::vec3::Vec3;
+
+/// An axis-aligned box, defined by its minimum and maximum corners.
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// A box that contains nothing; it's the neutral element for `union`.
+    pub const EMPTY: Aabb = Aabb {
+        min: Vec3 {
+            x: f64::INFINITY,
+            y: f64::INFINITY,
+            z: f64::INFINITY,
+        },
+        max: Vec3 {
+            x: f64::NEG_INFINITY,
+            y: f64::NEG_INFINITY,
+            z: f64::NEG_INFINITY,
+        },
+    };
+
+    /// Returns the smallest box that contains both boxes.
+    pub fn union(&self, other: Aabb) -> Aabb {
+        Aabb {
+            min: Vec3::new(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            max: Vec3::new(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        }
+    }
+
+    /// Returns the eight corners of the box.
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Vec3::new(a.x, a.y, a.z),
+            Vec3::new(b.x, a.y, a.z),
+            Vec3::new(a.x, b.y, a.z),
+            Vec3::new(b.x, b.y, a.z),
+            Vec3::new(a.x, a.y, b.z),
+            Vec3::new(b.x, a.y, b.z),
+            Vec3::new(a.x, b.y, b.z),
+            Vec3::new(b.x, b.y, b.z),
+        ]
+    }
+
+    /// Returns an axis-aligned box that contains this box after applying the transformation.
+    pub fn transformed(&self, transform: &Transform) -> Aabb {
+        self.corners().iter().fold(Aabb::EMPTY, |acc, &p| {
+            let p = transform.point(p);
+            acc.union(Aabb { min: p, max: p })
+        })
+    }
+
+    /// Returns the values `(t_min, t_max)` where the ray enters and leaves the box, or `None` if
+    /// it misses the box.
+    ///
+    /// Uses the slab method: the box is the intersection of three slabs, one per axis, and the ray
+    /// is inside the box where it's inside all three slabs.
+    pub fn intersect_ray(&self, ray: Ray) -> Option<(f64, f64)> {
+        let slabs = [
+            (self.min.x, self.max.x, ray.origin.x, ray.direction.x),
+            (self.min.y, self.max.y, ray.origin.y, ray.direction.y),
+            (self.min.z, self.max.z, ray.origin.z, ray.direction.z),
+        ];
+        let mut t_min = f64::NEG_INFINITY;
+        let mut t_max = f64::INFINITY;
+        for (min, max, o, d) in slabs {
+            if d == 0.0 {
+                if o < min || o > max {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (min - o) / d;
+            let t1 = (max - o) / d;
+            t_min = t_min.max(t0.min(t1));
+            t_max = t_max.min(t0.max(t1));
+        }
+        if t_min > t_max {
+            None
+        } else {
+            Some((t_min, t_max))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+    use std::f64::consts::FRAC_PI_4;
+
+    fn unit_box() -> Aabb {
+        Aabb {
+            min: Vec3::new(-1.0, -1.0, -1.0),
+            max: Vec3::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    #[test]
+    fn aabb_union() {
+        let a = unit_box();
+        let b = Aabb {
+            min: Vec3::new(0.0, 2.0, -3.0),
+            max: Vec3::new(0.5, 3.0, 0.0),
+        };
+        let u = a.union(b);
+        assert!(u.min.approx_eq(Vec3::new(-1.0, -1.0, -3.0)));
+        assert!(u.max.approx_eq(Vec3::new(1.0, 3.0, 1.0)));
+        let u = Aabb::EMPTY.union(a);
+        assert!(u.min.approx_eq(a.min));
+        assert!(u.max.approx_eq(a.max));
+    }
+
+    #[test]
+    fn aabb_transformed() {
+        let t = Transform::translation(Vec3::new(5.0, 0.0, 0.0))
+            * Transform::rotation(Vec3::new(0.0, 0.0, 1.0), FRAC_PI_4);
+        let b = unit_box().transformed(&t);
+        let r = 2f64.sqrt();
+        assert!(b.min.approx_eq(Vec3::new(5.0 - r, -r, -1.0)));
+        assert!(b.max.approx_eq(Vec3::new(5.0 + r, r, 1.0)));
+    }
+
+    #[test]
+    fn aabb_intersect_ray() {
+        let b = unit_box();
+        let ray = Ray {
+            origin: Vec3::new(0.0, 0.0, -5.0),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let (t0, t1) = b.intersect_ray(ray).unwrap();
+        assert!(t0.approx_eq(4.0));
+        assert!(t1.approx_eq(6.0));
+
+        // diagonal ray through a corner region
+        let ray = Ray {
+            origin: Vec3::new(-3.0, -2.5, 0.0),
+            direction: Vec3::new(1.0, 1.0, 0.0),
+            time: 0.0,
+        };
+        let (t0, t1) = b.intersect_ray(ray).unwrap();
+        assert!(t0.approx_eq(2.0));
+        assert!(t1.approx_eq(3.5));
+
+        // ray parallel to a slab, outside of it
+        let ray = Ray {
+            origin: Vec3::new(2.0, 0.0, -5.0),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        assert!(b.intersect_ray(ray).is_none());
+
+        // ray misses diagonally
+        let ray = Ray {
+            origin: Vec3::new(-3.0, 0.0, 0.0),
+            direction: Vec3::new(1.0, 2.0, 0.0),
+            time: 0.0,
+        };
+        assert!(b.intersect_ray(ray).is_none());
+    }
+}
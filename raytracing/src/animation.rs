@@ -0,0 +1,340 @@
+//! Defines keyframe animation of the camera, lights and objects, for rendering image sequences.
+
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::scene::{LightSource, Scene};
+use crate::transform::Transform;
+use crate::vec3::Vec3;
+
+/// A value that can be interpolated between keyframes.
+pub trait Lerp: Copy {
+    /// Returns the value a fraction `s` of the way from `self` to `other`.
+    fn lerp(self, other: Self, s: f64) -> Self;
+}
+
+impl Lerp for f64 {
+    fn lerp(self, other: f64, s: f64) -> f64 {
+        self + s * (other - self)
+    }
+}
+
+impl Lerp for Vec3 {
+    fn lerp(self, other: Vec3, s: f64) -> Vec3 {
+        self + s * (other - self)
+    }
+}
+
+impl Lerp for Color {
+    fn lerp(self, other: Color, s: f64) -> Color {
+        (1.0 - s) * self + s * other
+    }
+}
+
+/// How a value changes between two keyframes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    /// Changes at a constant rate.
+    Linear,
+
+    /// Starts and ends slowly (smoothstep), so movements ease in and out.
+    Smooth,
+}
+
+impl Interpolation {
+    /// Maps the fraction of the time between the keyframes that has passed to the fraction of the
+    /// change between the values.
+    fn ease(self, s: f64) -> f64 {
+        match self {
+            Interpolation::Linear => s,
+            Interpolation::Smooth => s * s * (3.0 - 2.0 * s),
+        }
+    }
+}
+
+/// The value of an animated property at a frame.
+#[derive(Debug, Clone, Copy)]
+pub struct Keyframe<T> {
+    pub frame: f64,
+    pub value: T,
+
+    /// How the value changes from this keyframe to the next.
+    pub interpolation: Interpolation,
+}
+
+/// The keyframes of an animated property, sorted by frame.
+#[derive(Debug, Clone)]
+pub struct Track<T> {
+    pub keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T: Lerp> Track<T> {
+    /// Returns the value at a frame. Before the first keyframe and after the last one, the value
+    /// is that of the first and last keyframe.
+    ///
+    /// Panics if the track has no keyframes.
+    pub fn value_at(&self, frame: f64) -> T {
+        let next = self.keyframes.iter().position(|k| k.frame > frame);
+        match next {
+            Some(0) => self.keyframes[0].value,
+            Some(i) => {
+                let (a, b) = (&self.keyframes[i - 1], &self.keyframes[i]);
+                let s = (frame - a.frame) / (b.frame - a.frame);
+                a.value.lerp(b.value, a.interpolation.ease(s))
+            }
+            None => self.keyframes.last().expect("track has no keyframes").value,
+        }
+    }
+}
+
+/// An animated property of the camera, a light or an object. Lights and objects are given by
+/// their index in the scene.
+#[derive(Debug, Clone)]
+pub enum Channel {
+    /// Moves the camera, which looks from `from` towards `to` (see `Camera::look_at`).
+    Camera {
+        from: Track<Vec3>,
+        to: Track<Vec3>,
+        up: Vec3,
+    },
+
+    /// Changes the camera's focus distance.
+    FocusDistance(Track<f64>),
+
+    /// Changes a light's intensity.
+    LightIntensity { light: usize, track: Track<f64> },
+
+    /// Moves a point light, or turns a directional light: the value is the position or the
+    /// direction.
+    LightPosition { light: usize, track: Track<Vec3> },
+
+    /// Moves an object by an offset, in the coordinate system it's placed in.
+    ObjectTranslation { object: usize, track: Track<Vec3> },
+
+    /// Rotates an object around an axis through the origin of its local coordinate system, e.g.
+    /// for a turntable. The value is the angle in radians.
+    ObjectRotation {
+        object: usize,
+        axis: Vec3,
+        track: Track<f64>,
+    },
+
+    /// Changes an object's color.
+    ObjectColor { object: usize, track: Track<Color> },
+}
+
+impl Channel {
+    /// Sets the property to its value at a frame.
+    fn apply(&self, camera: &mut Camera, scene: &mut Scene, frame: f64) {
+        match self {
+            Channel::Camera { from, to, up } => {
+                let at = Camera::look_at(from.value_at(frame), to.value_at(frame), *up);
+                camera.transform = at.transform;
+            }
+            Channel::FocusDistance(track) => camera.focus_distance = track.value_at(frame),
+            Channel::LightIntensity { light, track } => {
+                scene.lights[*light].intensity = track.value_at(frame)
+            }
+            Channel::LightPosition { light, track } => match &mut scene.lights[*light].source {
+                LightSource::Ambient => {}
+                LightSource::Point { position } => *position = track.value_at(frame),
+                LightSource::Directional { direction } => *direction = track.value_at(frame),
+            },
+            Channel::ObjectTranslation { object, track } => {
+                let object = &mut scene.objects[*object];
+                object.transform = Transform::translation(track.value_at(frame)) * object.transform;
+            }
+            Channel::ObjectRotation {
+                object,
+                axis,
+                track,
+            } => {
+                let object = &mut scene.objects[*object];
+                object.transform =
+                    object.transform * Transform::rotation(*axis, track.value_at(frame));
+            }
+            Channel::ObjectColor { object, track } => {
+                scene.objects[*object].material.color = track.value_at(frame)
+            }
+        }
+    }
+}
+
+/// An animation of a scene, rendered as a sequence of frames numbered from 1 to `frames`.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    pub frames: usize,
+    pub channels: Vec<Channel>,
+}
+
+impl Animation {
+    /// Returns the camera and the scene at a frame.
+    ///
+    /// Translations and rotations are relative to the objects' transformations in `scene`, so
+    /// this must be called with the scene that isn't animated yet.
+    ///
+    /// Panics if a channel refers to a light or object that doesn't exist.
+    pub fn frame(&self, camera: &Camera, scene: &Scene, frame: f64) -> (Camera, Scene) {
+        let mut camera = *camera;
+        let mut scene = scene.clone();
+        for channel in &self.channels {
+            channel.apply(&mut camera, &mut scene, frame);
+        }
+        (camera, scene)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+    use crate::scene::{Light, Material, Object, Shape, Sphere};
+    use std::f64::consts::PI;
+
+    fn track<T>(keyframes: &[(f64, T)], interpolation: Interpolation) -> Track<T>
+    where
+        T: Copy,
+    {
+        Track {
+            keyframes: keyframes
+                .iter()
+                .map(|&(frame, value)| Keyframe {
+                    frame,
+                    value,
+                    interpolation,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn track_value_at() {
+        let t = track(&[(1.0, 2.0), (5.0, 4.0), (7.0, 0.0)], Interpolation::Linear);
+        assert!(t.value_at(0.0).approx_eq(2.0));
+        assert!(t.value_at(1.0).approx_eq(2.0));
+        assert!(t.value_at(2.0).approx_eq(2.5));
+        assert!(t.value_at(5.0).approx_eq(4.0));
+        assert!(t.value_at(6.5).approx_eq(1.0));
+        assert!(t.value_at(10.0).approx_eq(0.0));
+
+        // smooth interpolation eases in and out, but matches linear interpolation halfway
+        let t = track(&[(1.0, 2.0), (5.0, 4.0)], Interpolation::Smooth);
+        assert!(t.value_at(2.0).approx_eq(2.3125));
+        assert!(t.value_at(3.0).approx_eq(3.0));
+        assert!(t.value_at(4.0).approx_eq(3.6875));
+
+        let t = track(
+            &[(1.0, Color::BLACK), (3.0, Color(1.0, 0.5, 0.0))],
+            Interpolation::Linear,
+        );
+        assert!(t.value_at(2.0).approx_eq(Color(0.5, 0.25, 0.0)));
+    }
+
+    #[test]
+    fn animation_frame() {
+        let scene = Scene {
+            background_color: Color::BLACK,
+            lights: vec![Light {
+                intensity: 0.5,
+                source: LightSource::Point {
+                    position: Vec3::new(0.0, 0.0, 0.0),
+                },
+            }],
+            objects: vec![Object {
+                shape: Shape::Sphere(Sphere {
+                    center: Vec3::new(0.0, 0.0, 0.0),
+                    radius: 1.0,
+                }),
+                material: Material::BLACK,
+                transform: Transform::translation(Vec3::new(1.0, 0.0, 0.0)),
+                motion: None,
+            }],
+            instances: vec![],
+            ambient_occlusion: None,
+        };
+        let animation = Animation {
+            frames: 3,
+            channels: vec![
+                Channel::Camera {
+                    from: track(
+                        &[
+                            (1.0, Vec3::new(0.0, 0.0, 0.0)),
+                            (3.0, Vec3::new(2.0, 0.0, 0.0)),
+                        ],
+                        Interpolation::Linear,
+                    ),
+                    to: track(&[(1.0, Vec3::new(0.0, 0.0, 5.0))], Interpolation::Linear),
+                    up: Vec3::new(0.0, 1.0, 0.0),
+                },
+                Channel::LightIntensity {
+                    light: 0,
+                    track: track(&[(1.0, 0.5), (3.0, 1.0)], Interpolation::Linear),
+                },
+                Channel::LightPosition {
+                    light: 0,
+                    track: track(
+                        &[
+                            (1.0, Vec3::new(0.0, 0.0, 0.0)),
+                            (3.0, Vec3::new(0.0, 4.0, 0.0)),
+                        ],
+                        Interpolation::Linear,
+                    ),
+                },
+                Channel::ObjectRotation {
+                    object: 0,
+                    axis: Vec3::new(0.0, 1.0, 0.0),
+                    track: track(&[(1.0, 0.0), (3.0, PI)], Interpolation::Linear),
+                },
+                Channel::ObjectTranslation {
+                    object: 0,
+                    track: track(
+                        &[
+                            (1.0, Vec3::new(0.0, 0.0, 0.0)),
+                            (3.0, Vec3::new(0.0, 0.0, 2.0)),
+                        ],
+                        Interpolation::Linear,
+                    ),
+                },
+                Channel::ObjectColor {
+                    object: 0,
+                    track: track(
+                        &[(1.0, Color::BLACK), (3.0, Color::WHITE)],
+                        Interpolation::Linear,
+                    ),
+                },
+            ],
+        };
+
+        let (camera, frame) = animation.frame(&Camera::DEFAULT, &scene, 2.0);
+        assert!(camera.position().approx_eq(Vec3::new(1.0, 0.0, 0.0)));
+        assert!(camera
+            .transform
+            .vector(Vec3::new(0.0, 0.0, 1.0))
+            .approx_eq(Vec3::new(-1.0, 0.0, 5.0).normalized()));
+        let light = &frame.lights[0];
+        assert!(light.intensity.approx_eq(0.75));
+        match light.source {
+            LightSource::Point { position } => {
+                assert!(position.approx_eq(Vec3::new(0.0, 2.0, 0.0)))
+            }
+            _ => panic!("light source changed"),
+        }
+
+        // rotated by 90 degrees in place, then translated
+        let object = &frame.objects[0];
+        assert!(object
+            .transform
+            .point(Vec3::new(1.0, 0.0, 0.0))
+            .approx_eq(Vec3::new(1.0, 0.0, 0.0)));
+        assert!(object
+            .transform
+            .point(Vec3::new(0.0, 0.0, 0.0))
+            .approx_eq(Vec3::new(1.0, 0.0, 1.0)));
+        assert!(object.material.color.approx_eq(Color(0.5, 0.5, 0.5)));
+
+        // the original scene doesn't change
+        assert!(scene.objects[0]
+            .transform
+            .point(Vec3::new(0.0, 0.0, 0.0))
+            .approx_eq(Vec3::new(1.0, 0.0, 0.0)));
+    }
+}
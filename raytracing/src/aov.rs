@@ -0,0 +1,207 @@
+//! Defines the auxiliary outputs of a render (AOVs, "arbitrary output variables"): data for each
+//! pixel besides the final image, like depth and surface normals, for compositing and debugging.
+
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::vec3::Vec3;
+
+/// A rectangular buffer of values, one for each pixel of an image.
+///
+/// Pixels use the same coordinates as `Canvas::put_pixel`, with the origin in the center.
+#[derive(Debug, Clone)]
+pub struct Buffer<T> {
+    width: usize,
+    height: usize,
+    values: Vec<T>,
+}
+
+impl<T: Copy> Buffer<T> {
+    /// Returns a new buffer with all pixels set to `value`.
+    pub fn new(width: usize, height: usize, value: T) -> Buffer<T> {
+        Buffer {
+            width,
+            height,
+            values: vec![value; width * height],
+        }
+    }
+
+    /// Returns the value of a pixel, or `None` if it's outside the buffer.
+    pub fn get_pixel(&self, x: i32, y: i32) -> Option<T> {
+        self.index(x, y).map(|i| self.values[i])
+    }
+
+    /// Sets the value of a pixel. Pixels outside the buffer are ignored.
+    pub fn put_pixel(&mut self, x: i32, y: i32, value: T) {
+        if let Some(i) = self.index(x, y) {
+            self.values[i] = value;
+        }
+    }
+
+    /// Converts the buffer to an image, with `f` mapping each value to a color.
+    pub fn to_canvas(&self, f: impl Fn(T) -> Color) -> Canvas {
+        let mut canvas = Canvas::new(self.width, self.height);
+        let w2 = (self.width / 2) as i32;
+        let h2 = (self.height / 2) as i32;
+        for x in -w2..w2 {
+            for y in -h2..h2 {
+                canvas.put_pixel(x, y, f(self.get_pixel(x, y).unwrap()));
+            }
+        }
+        canvas
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        let w2 = (self.width / 2) as i32;
+        let h2 = (self.height / 2) as i32;
+        if x < -w2 || x >= w2 || y < -h2 || y >= h2 {
+            return None;
+        }
+        Some((h2 - 1 - y) as usize * self.width + (w2 + x) as usize)
+    }
+}
+
+/// The auxiliary outputs of a render.
+///
+/// Colors are averaged over the samples for anti-aliasing, like the final image. Depth, normals
+/// and object indices can't be averaged meaningfully, so they come from the ray through the
+/// center of the pixel, or the ray closest to it if the projection has no ray through the center,
+/// e.g. at the edge of a fisheye image circle.
+#[derive(Debug, Clone)]
+pub struct Aovs {
+    /// The value `t` where the camera ray hits a surface, with the projection plane at `t = 1`, or
+    /// infinity if it hits nothing.
+    pub depth: Buffer<f64>,
+
+    /// The normalized surface normal in world coordinates, facing the camera, or zero if the ray
+    /// hits nothing.
+    pub normal: Buffer<Vec3>,
+
+    /// The color of the surface without any lighting, or black where rays hit nothing.
+    pub albedo: Buffer<Color>,
+
+    /// The index of the object that's hit: objects are numbered in the order of
+    /// `Scene::objects`, followed by `Scene::instances`.
+    pub object: Buffer<Option<usize>>,
+
+    /// The light from the light sources reflected directly to the camera, or the background color
+    /// where rays hit nothing.
+    pub direct: Buffer<Color>,
+
+    /// The light reflected from other objects. Together with `direct`, this adds up to the final
+    /// image.
+    pub reflected: Buffer<Color>,
+}
+
+impl Aovs {
+    /// Returns empty buffers for an image of the given size.
+    pub fn new(width: usize, height: usize) -> Aovs {
+        Aovs {
+            depth: Buffer::new(width, height, f64::INFINITY),
+            normal: Buffer::new(width, height, Vec3::new(0.0, 0.0, 0.0)),
+            albedo: Buffer::new(width, height, Color::BLACK),
+            object: Buffer::new(width, height, None),
+            direct: Buffer::new(width, height, Color::BLACK),
+            reflected: Buffer::new(width, height, Color::BLACK),
+        }
+    }
+
+    /// Returns the depth as a grayscale image, from white for the nearest surface to black for the
+    /// farthest, and for pixels where nothing is hit.
+    pub fn depth_image(&self) -> Canvas {
+        let finite = self.depth.values.iter().filter(|t| t.is_finite());
+        let min = finite.clone().fold(f64::INFINITY, |a, &b| a.min(b));
+        let max = finite.fold(f64::NEG_INFINITY, |a, &b| a.max(b));
+        self.depth.to_canvas(|t| {
+            if !t.is_finite() {
+                Color::BLACK
+            } else if max > min {
+                let gray = 1.0 - (t - min) / (max - min);
+                Color(gray, gray, gray)
+            } else {
+                Color::WHITE
+            }
+        })
+    }
+
+    /// Returns the normals as an image, mapping the coordinates from [-1, 1] to colors in [0, 1].
+    pub fn normal_image(&self) -> Canvas {
+        self.normal
+            .to_canvas(|n| Color(0.5 * (n.x + 1.0), 0.5 * (n.y + 1.0), 0.5 * (n.z + 1.0)))
+    }
+
+    /// Returns the object indices as an image, with a different color for each object, and black
+    /// for pixels where nothing is hit.
+    pub fn object_image(&self) -> Canvas {
+        self.object.to_canvas(|object| match object {
+            // spread the hues with the golden ratio, so neighboring indices look different
+            Some(i) => hue((i as f64 * 0.618_033_988_75).fract()),
+            None => Color::BLACK,
+        })
+    }
+}
+
+/// Returns a bright, saturated color with the given hue, from 0 to 1.
+fn hue(h: f64) -> Color {
+    let h = 6.0 * h;
+    let channel = |offset: f64| {
+        let d = ((h - offset).rem_euclid(6.0) - 3.0).abs();
+        (d - 1.0).clamp(0.0, 1.0) * 0.7 + 0.3
+    };
+    Color(channel(0.0), channel(2.0), channel(4.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+
+    #[test]
+    fn buffer_pixels() {
+        let mut b = Buffer::new(4, 2, 0.0);
+        b.put_pixel(-2, -1, 1.0); // bottom left
+        b.put_pixel(1, 0, 2.0); // top right
+        b.put_pixel(2, 0, 3.0); // outside
+        assert_eq!(b.values, vec![0.0, 0.0, 0.0, 2.0, 1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(b.get_pixel(-2, -1), Some(1.0));
+        assert_eq!(b.get_pixel(0, 0), Some(0.0));
+        assert_eq!(b.get_pixel(0, 1), None);
+
+        let mut want = Canvas::new(4, 2);
+        want.put_pixel(-2, -1, Color(1.0, 0.5, 0.0));
+        want.put_pixel(1, 0, Color(2.0, 1.0, 0.0));
+        assert!(b.to_canvas(|v| Color(v, 0.5 * v, 0.0)).approx_eq(&want));
+    }
+
+    #[test]
+    fn aovs_images() {
+        let mut aovs = Aovs::new(2, 2);
+        aovs.depth.put_pixel(-1, 0, 2.0);
+        aovs.depth.put_pixel(0, 0, 4.0);
+        aovs.depth.put_pixel(-1, -1, 3.0);
+        let mut want = Canvas::new(2, 2);
+        want.put_pixel(-1, 0, Color::WHITE);
+        want.put_pixel(-1, -1, Color(0.5, 0.5, 0.5));
+        assert!(aovs.depth_image().approx_eq(&want));
+
+        aovs.normal.put_pixel(0, 0, Vec3::new(0.0, 0.0, -1.0));
+        let mut want = Canvas::new(2, 2);
+        for (x, y) in [(-1, 0), (-1, -1), (0, -1)] {
+            want.put_pixel(x, y, Color(0.5, 0.5, 0.5));
+        }
+        want.put_pixel(0, 0, Color(0.5, 0.5, 0.0));
+        assert!(aovs.normal_image().approx_eq(&want));
+
+        // objects get different colors, and pixels without objects are black
+        aovs.object.put_pixel(-1, 0, Some(0));
+        aovs.object.put_pixel(0, 0, Some(1));
+        aovs.object.put_pixel(0, -1, Some(1));
+        let image = aovs.object_image();
+        let mut want = Canvas::new(2, 2);
+        want.put_pixel(-1, 0, hue(0.0));
+        want.put_pixel(0, 0, hue(0.618_033_988_75));
+        want.put_pixel(0, -1, hue(0.618_033_988_75));
+        assert!(image.approx_eq(&want));
+        assert!(!hue(0.0).approx_eq(hue(0.618_033_988_75)));
+        assert!(hue(0.0).approx_eq(Color(1.0, 0.3, 0.3)));
+    }
+}
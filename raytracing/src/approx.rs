@@ -14,6 +14,12 @@ impl ApproxEq for f64 {
     }
 }
 
+impl ApproxEq for (f64, f64) {
+    fn approx_eq(self, other: (f64, f64)) -> bool {
+        self.0.approx_eq(other.0) && self.1.approx_eq(other.1)
+    }
+}
+
 impl ApproxEq for &Vec<f64> {
     fn approx_eq(self, other: &Vec<f64>) -> bool {
         if self.len() != other.len() {
@@ -46,6 +52,14 @@ mod tests {
         assert!(!y.approx_eq(y - LARGE));
     }
 
+    #[test]
+    fn approx_eq_for_pair() {
+        let p = (0.1, 0.2);
+        assert!(p.approx_eq((0.1 + SMALL, 0.2 - SMALL)));
+        assert!(!p.approx_eq((0.1 + LARGE, 0.2)));
+        assert!(!p.approx_eq((0.1, 0.2 - LARGE)));
+    }
+
     #[test]
     fn approx_eq_for_vec() {
         let v = vec![0.1, 0.2, 0.3];
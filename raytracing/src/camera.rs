@@ -0,0 +1,439 @@
+//! Defines a type for the camera that a scene is viewed from.
+
+use crate::matrix::{Mat3, Mat4};
+use crate::random;
+use crate::ray::Ray;
+use crate::transform::Transform;
+use crate::vec3::Vec3;
+use std::f64::consts::PI;
+
+/// The camera's position and orientation in the scene.
+///
+/// In its own coordinate system, the camera is at the origin looking along the z axis, with the x
+/// axis pointing right and the y axis pointing up. `transform` maps this to world coordinates.
+///
+/// By default, the camera is a pinhole camera with everything in focus. For depth of field, set
+/// `aperture` to the radius of the lens: then only objects at `focus_distance` are sharp.
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    pub transform: Transform,
+
+    /// The radius of the lens, or 0 for a pinhole camera.
+    pub aperture: f64,
+
+    /// The distance along the viewing direction at which objects are in focus.
+    pub focus_distance: f64,
+
+    /// How the scene is projected onto the image.
+    pub projection: Projection,
+
+    /// The time at which the shutter opens, in frames (see `Ray::time`).
+    pub shutter_open: f64,
+
+    /// The time at which the shutter closes. Rays are cast at random times between the shutter
+    /// opening and closing, so moving objects are blurred. If it's the same as `shutter_open`,
+    /// there's no motion blur.
+    pub shutter_close: f64,
+}
+
+impl Camera {
+    /// A camera at the origin, looking along the z axis.
+    pub const DEFAULT: Camera = Camera {
+        transform: Transform::IDENTITY,
+        aperture: 0.0,
+        focus_distance: 1.0,
+        projection: Projection::Perspective,
+        shutter_open: 0.0,
+        shutter_close: 0.0,
+    };
+
+    /// Returns a camera at `from`, looking towards `to`, with the `up` direction pointing up in
+    /// the image (as far as possible).
+    ///
+    /// Panics if `up` is parallel to the viewing direction.
+    pub fn look_at(from: Vec3, to: Vec3, up: Vec3) -> Camera {
+        let z = (to - from).normalized();
+        let x = up.cross(z);
+        assert!(
+            x.len() > 0.0,
+            "up must not be parallel to the viewing direction"
+        );
+        let x = x.normalized();
+        let y = z.cross(x);
+        let rotation = Mat3([[x.x, y.x, z.x], [x.y, y.y, z.y], [x.z, y.z, z.z]]);
+        Camera {
+            transform: Transform::new(Mat4::affine(rotation, from)).unwrap(),
+            ..Camera::DEFAULT
+        }
+    }
+
+    /// Returns the camera's position in world coordinates.
+    pub fn position(&self) -> Vec3 {
+        self.transform.point(Vec3::new(0.0, 0.0, 0.0))
+    }
+
+    /// Returns the cameras for the left and right eye, for stereo images. The eyes are
+    /// `interocular_distance` apart, centered on this camera and looking in the same direction.
+    ///
+    /// Their images are shifted sideways (an off-axis projection) so that objects at
+    /// `convergence_distance` line up, and appear at the depth of the screen. Closer objects
+    /// appear in front of the screen, and objects further away behind it. For parallel cameras,
+    /// without the shift, use an infinite convergence distance.
+    ///
+    /// Panics if `convergence_distance` isn't positive.
+    pub fn stereo_pair(
+        &self,
+        interocular_distance: f64,
+        convergence_distance: f64,
+    ) -> (Camera, Camera) {
+        assert!(
+            convergence_distance > 0.0,
+            "convergence distance must be positive"
+        );
+        let eye = |side: f64| {
+            let offset = side * interocular_distance / 2.0;
+            // a shear that turns rays along the z axis towards the point of convergence
+            let shear = Mat3([
+                [1.0, 0.0, -offset / convergence_distance],
+                [0.0, 1.0, 0.0],
+                [0.0, 0.0, 1.0],
+            ]);
+            let local = Mat4::affine(shear, Vec3::new(offset, 0.0, 0.0));
+            Camera {
+                transform: self.transform * Transform::new(local).unwrap(),
+                ..*self
+            }
+        };
+        (eye(-1.0), eye(1.0))
+    }
+
+    /// Returns a ray in world coordinates for a ray in camera coordinates, e.g. one from
+    /// `Projection::ray`.
+    ///
+    /// The ray is cast at a random time while the shutter is open. With an aperture, the ray
+    /// starts at a random point on the lens instead, aimed so that it crosses the original ray at
+    /// the focus distance. The lens only affects rays going forward, so panoramas are only partly
+    /// blurred.
+    pub fn ray(&self, ray: Ray) -> Ray {
+        let time = if self.shutter_close > self.shutter_open {
+            let s = random::with_rng(|rng| rng.next_f64());
+            self.shutter_open + s * (self.shutter_close - self.shutter_open)
+        } else {
+            self.shutter_open
+        };
+        let ray = Ray { time, ..ray };
+        if self.aperture <= 0.0 || ray.direction.z <= 0.0 {
+            return self.transform.ray(ray);
+        }
+        let scale = self.focus_distance / ray.direction.z;
+        let focus = ray.at(scale);
+        let (x, y) = random::with_rng(|rng| rng.in_unit_disk());
+        let origin = ray.origin + Vec3::new(self.aperture * x, self.aperture * y, 0.0);
+        // scaled so that the ray reaches the projection plane at t = 1, like the original ray
+        self.transform.ray(Ray {
+            origin,
+            direction: (focus - origin) / scale,
+            time: ray.time,
+        })
+    }
+}
+
+/// How the camera projects the scene onto the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// A perspective projection, like a pinhole camera: rays start at the camera's position and go
+    /// through the viewport, so objects further away look smaller.
+    Perspective,
+
+    /// An orthographic projection: rays start across the viewport and are all parallel to the
+    /// viewing direction, so objects look the same size at any distance, like in technical
+    /// drawings.
+    Orthographic,
+
+    /// An equirectangular (latitude-longitude) panorama of everything around the camera, e.g. for
+    /// VR or environment maps. The viewport's width covers 360 degrees of longitude and its height
+    /// 180 degrees of latitude, so the image should be twice as wide as it is high.
+    Equirectangular,
+
+    /// A fisheye lens, with a circular image that fits the viewport and covers `field_of_view`
+    /// (an angle in radians, which can be more than 180 degrees). Outside the circle, nothing is
+    /// rendered.
+    Fisheye {
+        field_of_view: f64,
+        mapping: FisheyeMapping,
+    },
+}
+
+/// How a fisheye lens maps the angle from the viewing direction to the distance from the center of
+/// the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FisheyeMapping {
+    /// The distance is proportional to the angle.
+    Equidistant,
+
+    /// The distance is proportional to `sin(angle / 2)`, which keeps areas the same on the whole
+    /// image.
+    Equisolid,
+}
+
+impl Projection {
+    /// Returns the ray, in camera coordinates, through point `p` on the viewport, which is the
+    /// rectangle at `z = d` (the distance to the projection plane) with the given size that is
+    /// mapped to the canvas. Returns `None` if there is no ray for the point, e.g. outside the
+    /// circle of a fisheye image.
+    ///
+    /// For perspective and orthographic projections, the ray reaches the viewport at `t = 1`. For
+    /// the other projections, it's at distance `d` from the camera at `t = 1`.
+    pub fn ray(&self, p: Vec3, viewport_width: f64, viewport_height: f64) -> Option<Ray> {
+        let origin = Vec3::new(0.0, 0.0, 0.0);
+        let ray = match *self {
+            Projection::Perspective => Ray {
+                origin,
+                direction: p,
+                time: 0.0,
+            },
+            Projection::Orthographic => Ray {
+                origin: Vec3::new(p.x, p.y, 0.0),
+                direction: Vec3::new(0.0, 0.0, p.z),
+                time: 0.0,
+            },
+            Projection::Equirectangular => {
+                let longitude = 2.0 * PI * p.x / viewport_width;
+                let latitude = PI * p.y / viewport_height;
+                Ray {
+                    origin,
+                    direction: p.z * direction(longitude, latitude),
+                    time: 0.0,
+                }
+            }
+            Projection::Fisheye {
+                field_of_view,
+                mapping,
+            } => {
+                // the distance from the center, as a fraction of the radius of the image circle
+                let radius = 0.5 * viewport_width.min(viewport_height);
+                let r = (p.x * p.x + p.y * p.y).sqrt() / radius;
+                if r > 1.0 {
+                    return None;
+                }
+                let theta = match mapping {
+                    FisheyeMapping::Equidistant => r * field_of_view / 2.0,
+                    FisheyeMapping::Equisolid => 2.0 * (r * (field_of_view / 4.0).sin()).asin(),
+                };
+                let phi = p.y.atan2(p.x);
+                Ray {
+                    origin,
+                    direction: p.z
+                        * Vec3::new(
+                            theta.sin() * phi.cos(),
+                            theta.sin() * phi.sin(),
+                            theta.cos(),
+                        ),
+                    time: 0.0,
+                }
+            }
+        };
+        Some(ray)
+    }
+}
+
+/// Returns the unit vector for the given longitude and latitude, with longitude 0 along the z axis,
+/// increasing towards the x axis.
+fn direction(longitude: f64, latitude: f64) -> Vec3 {
+    Vec3::new(
+        longitude.sin() * latitude.cos(),
+        latitude.sin(),
+        longitude.cos() * latitude.cos(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+
+    #[test]
+    fn camera_look_at() {
+        let camera = Camera::look_at(
+            Vec3::new(1.0, 2.0, 3.0),
+            Vec3::new(1.0, 2.0, 5.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        assert!(camera
+            .transform
+            .approx_eq(Transform::translation(Vec3::new(1.0, 2.0, 3.0))));
+        assert!(camera.position().approx_eq(Vec3::new(1.0, 2.0, 3.0)));
+
+        // looking down the negative x axis
+        let camera = Camera::look_at(
+            Vec3::new(5.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        let t = camera.transform;
+        assert!(t
+            .vector(Vec3::new(0.0, 0.0, 1.0))
+            .approx_eq(Vec3::new(-1.0, 0.0, 0.0)));
+        assert!(t
+            .vector(Vec3::new(0.0, 1.0, 0.0))
+            .approx_eq(Vec3::new(0.0, 1.0, 0.0)));
+        assert!(t
+            .vector(Vec3::new(1.0, 0.0, 0.0))
+            .approx_eq(Vec3::new(0.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn camera_ray() {
+        let camera = Camera::look_at(
+            Vec3::new(1.0, 2.0, 3.0),
+            Vec3::new(1.0, 2.0, 5.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        let direction = Vec3::new(0.5, -0.5, 1.0);
+        let ray = camera.ray(Projection::Perspective.ray(direction, 1.0, 1.0).unwrap());
+        assert!(ray.origin.approx_eq(camera.position()));
+        assert!(ray.direction.approx_eq(direction));
+
+        // with a lens, rays start on the lens and meet at the focus distance
+        let camera = Camera {
+            aperture: 0.2,
+            focus_distance: 4.0,
+            ..camera
+        };
+        let focus = Vec3::new(3.0, 0.0, 7.0);
+        for _ in 0..100 {
+            let ray = camera.ray(Projection::Perspective.ray(direction, 1.0, 1.0).unwrap());
+            assert!((ray.origin - camera.position()).len() <= 0.2);
+            assert!(ray.origin.z.approx_eq(3.0));
+            assert!(ray.direction.z.approx_eq(1.0));
+            assert!(ray.at(4.0).approx_eq(focus));
+        }
+    }
+
+    #[test]
+    fn camera_shutter() {
+        let ray = Projection::Perspective
+            .ray(Vec3::new(0.0, 0.0, 1.0), 1.0, 1.0)
+            .unwrap();
+        let camera = Camera {
+            shutter_open: 0.5,
+            shutter_close: 0.5,
+            ..Camera::DEFAULT
+        };
+        assert_eq!(camera.ray(ray).time, 0.5);
+
+        let camera = Camera {
+            shutter_open: 0.25,
+            shutter_close: 0.75,
+            ..Camera::DEFAULT
+        };
+        let times: Vec<f64> = (0..100).map(|_| camera.ray(ray).time).collect();
+        assert!(times.iter().all(|t| (0.25..0.75).contains(t)));
+        let mean = times.iter().sum::<f64>() / 100.0;
+        assert!((mean - 0.5).abs() < 0.05, "mean == {}", mean);
+    }
+
+    #[test]
+    fn camera_stereo_pair() {
+        let camera = Camera::look_at(
+            Vec3::new(1.0, 2.0, 3.0),
+            Vec3::new(1.0, 2.0, 5.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        let (left, right) = camera.stereo_pair(0.2, 4.0);
+        assert!(left.position().approx_eq(Vec3::new(0.9, 2.0, 3.0)));
+        assert!(right.position().approx_eq(Vec3::new(1.1, 2.0, 3.0)));
+
+        // the rays through the center of the image meet at the convergence distance
+        let center = Projection::Perspective.ray(Vec3::new(0.0, 0.0, 1.0), 1.0, 1.0);
+        let convergence = Vec3::new(1.0, 2.0, 7.0);
+        assert!(left.ray(center.unwrap()).at(4.0).approx_eq(convergence));
+        assert!(right.ray(center.unwrap()).at(4.0).approx_eq(convergence));
+
+        // and so do the rays through any other point, since the images line up there
+        let ray = Projection::Perspective.ray(Vec3::new(0.3, 0.2, 1.0), 1.0, 1.0);
+        let (l, r) = (left.ray(ray.unwrap()), right.ray(ray.unwrap()));
+        assert!(l.at(4.0).approx_eq(r.at(4.0)));
+        assert!(l.at(4.0).approx_eq(camera.ray(ray.unwrap()).at(4.0)));
+
+        // with an infinite convergence distance, the cameras are parallel: there's no shear
+        let (left, right) = camera.stereo_pair(0.2, f64::INFINITY);
+        assert!(left.position().approx_eq(Vec3::new(0.9, 2.0, 3.0)));
+        assert!(right.position().approx_eq(Vec3::new(1.1, 2.0, 3.0)));
+        let (l, r) = (left.ray(ray.unwrap()), right.ray(ray.unwrap()));
+        let direction = camera.ray(ray.unwrap()).direction;
+        assert!(l.direction.approx_eq(direction));
+        assert!(r.direction.approx_eq(direction));
+        assert!((r.at(4.0) - l.at(4.0)).approx_eq(Vec3::new(0.2, 0.0, 0.0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "convergence distance must be positive")]
+    fn camera_stereo_pair_zero_convergence() {
+        Camera::DEFAULT.stereo_pair(0.2, 0.0);
+    }
+
+    #[test]
+    fn projection_ray() {
+        let p = Vec3::new(0.5, -0.25, 2.0);
+        let ray = Projection::Perspective.ray(p, 2.0, 1.0).unwrap();
+        assert!(ray.origin.approx_eq(Vec3::new(0.0, 0.0, 0.0)));
+        assert!(ray.at(1.0).approx_eq(p));
+
+        // parallel rays, starting across the viewport
+        let ray = Projection::Orthographic.ray(p, 2.0, 1.0).unwrap();
+        assert!(ray.origin.approx_eq(Vec3::new(0.5, -0.25, 0.0)));
+        assert!(ray.direction.approx_eq(Vec3::new(0.0, 0.0, 2.0)));
+        assert!(ray.at(1.0).approx_eq(p));
+    }
+
+    #[test]
+    fn panoramic_projection_ray() {
+        let s = 0.5f64.sqrt();
+        let equirectangular = Projection::Equirectangular;
+        let cases = [
+            (Vec3::new(0.0, 0.0, 2.0), Vec3::new(0.0, 0.0, 2.0)),
+            (Vec3::new(0.5, 0.0, 2.0), Vec3::new(2.0, 0.0, 0.0)),
+            (Vec3::new(-1.0, 0.0, 2.0), Vec3::new(0.0, 0.0, -2.0)),
+            (Vec3::new(0.25, 0.25, 2.0), Vec3::new(1.0, 2.0 * s, 1.0)),
+            (Vec3::new(0.0, -0.5, 2.0), Vec3::new(0.0, -2.0, 0.0)),
+        ];
+        for (p, want) in cases {
+            let got = equirectangular.ray(p, 2.0, 1.0).unwrap().direction;
+            assert!(got.approx_eq(want), "{:?} -> {:?}", p, got);
+        }
+
+        // a 180 degree fisheye, with the image circle filling the height of the viewport
+        let fisheye = |mapping| Projection::Fisheye {
+            field_of_view: PI,
+            mapping,
+        };
+        for mapping in [FisheyeMapping::Equidistant, FisheyeMapping::Equisolid] {
+            let ray = |x, y| fisheye(mapping).ray(Vec3::new(x, y, 1.0), 2.0, 1.0);
+            assert!(ray(0.0, 0.0)
+                .unwrap()
+                .direction
+                .approx_eq(Vec3::new(0.0, 0.0, 1.0)));
+            assert!(ray(0.0, 0.5)
+                .unwrap()
+                .direction
+                .approx_eq(Vec3::new(0.0, 1.0, 0.0)));
+            assert!(ray(-0.5, 0.0)
+                .unwrap()
+                .direction
+                .approx_eq(Vec3::new(-1.0, 0.0, 0.0)));
+            assert!(ray(0.5, 0.1).is_none());
+        }
+        // halfway to the edge, the mappings differ
+        let direction = |mapping| {
+            fisheye(mapping)
+                .ray(Vec3::new(0.25, 0.0, 1.0), 2.0, 1.0)
+                .unwrap()
+                .direction
+        };
+        let angle = |v: Vec3| v.x.atan2(v.z);
+        assert!(angle(direction(FisheyeMapping::Equidistant)).approx_eq(PI / 4.0));
+        let want = 2.0 * (0.5 * (PI / 4.0).sin()).asin();
+        assert!(angle(direction(FisheyeMapping::Equisolid)).approx_eq(want));
+    }
+}
@@ -41,6 +41,51 @@ impl Canvas {
         self.pixels[y * self.width + x] = color;
     }
 
+    /// Returns a stereo image with the images for the left and right eye side by side, e.g. for VR
+    /// headsets or cross-eyed viewing (after swapping the images).
+    ///
+    /// Panics if the images don't have the same height.
+    pub fn side_by_side(left: &Canvas, right: &Canvas) -> Canvas {
+        assert_eq!(
+            left.height, right.height,
+            "images must have the same height"
+        );
+        let pixels = left
+            .pixels
+            .chunks(left.width)
+            .zip(right.pixels.chunks(right.width))
+            .flat_map(|(l, r)| l.iter().chain(r.iter()).copied())
+            .collect();
+        Canvas {
+            width: left.width + right.width,
+            height: left.height,
+            pixels,
+        }
+    }
+
+    /// Returns a red-cyan anaglyph of the images for the left and right eye, for viewing with
+    /// red-cyan glasses: the red channel comes from the left image, and the green and blue
+    /// channels from the right.
+    ///
+    /// Panics if the images don't have the same size.
+    pub fn anaglyph(left: &Canvas, right: &Canvas) -> Canvas {
+        assert!(
+            left.width == right.width && left.height == right.height,
+            "images must have the same size"
+        );
+        let pixels = left
+            .pixels
+            .iter()
+            .zip(right.pixels.iter())
+            .map(|(l, r)| Color(l.0, r.1, r.2))
+            .collect();
+        Canvas {
+            width: left.width,
+            height: left.height,
+            pixels,
+        }
+    }
+
     /// Save the image to a file in binary PPM format.
     pub fn save_to_ppm(&self, path: &str) -> io::Result<()> {
         let mut f = File::create(path)?;
@@ -61,6 +106,78 @@ impl Canvas {
             .collect();
         f.write_all(&buf)
     }
+
+    /// Save the image to a file in PNG format.
+    pub fn save_to_png(&self, path: &str) -> io::Result<()> {
+        let mut f = File::create(path)?;
+        self.write_to_png(&mut f)
+    }
+
+    /// Writes the image as an 8-bit RGB PNG. The image data isn't compressed, which keeps the
+    /// encoder simple: the zlib stream consists of "stored" deflate blocks.
+    fn write_to_png(&self, f: &mut dyn Write) -> io::Result<()> {
+        f.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+        let mut header = vec![];
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        // bit depth 8, color type RGB, default compression, filter and interlace methods
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+        write_png_chunk(f, b"IHDR", &header)?;
+
+        // each row starts with the filter type, which is 0 (none)
+        let mut raw = Vec::with_capacity(self.height * (1 + 3 * self.width));
+        for row in self.pixels.chunks(self.width.max(1)) {
+            raw.push(0);
+            for pixel in row {
+                let (r, g, b) = pixel.to_u8();
+                raw.extend_from_slice(&[r, g, b]);
+            }
+        }
+        let mut data = vec![0x78, 0x01];
+        let mut blocks = raw.chunks(0xffff).peekable();
+        if blocks.peek().is_none() {
+            data.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+        }
+        while let Some(block) = blocks.next() {
+            let last = blocks.peek().is_none();
+            let len = block.len() as u16;
+            data.push(last as u8);
+            data.extend_from_slice(&len.to_le_bytes());
+            data.extend_from_slice(&(!len).to_le_bytes());
+            data.extend_from_slice(block);
+        }
+        data.extend_from_slice(&adler32(&raw).to_be_bytes());
+        write_png_chunk(f, b"IDAT", &data)?;
+
+        write_png_chunk(f, b"IEND", &[])
+    }
+}
+
+/// Writes a PNG chunk with its length and checksum.
+fn write_png_chunk(f: &mut dyn Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    f.write_all(&(data.len() as u32).to_be_bytes())?;
+    f.write_all(kind)?;
+    f.write_all(data)?;
+    let crc = !kind.iter().chain(data).fold(!0, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    });
+    f.write_all(&crc.to_be_bytes())
+}
+
+/// Returns the Adler-32 checksum of the data, as used by zlib.
+fn adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), &byte| {
+        let a = (a + byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    (b << 16) | a
 }
 
 impl ApproxEq for &Canvas {
@@ -132,6 +249,28 @@ mod tests {
         assert!(c.approx_eq(&want));
     }
 
+    #[test]
+    fn canvas_stereo() {
+        let left = sample_canvas();
+        let mut right = Canvas::new(4, 2);
+        right.put_pixel(0, 0, Color(0.2, 0.4, 0.6));
+
+        let c = Canvas::side_by_side(&left, &right);
+        assert_eq!((c.width, c.height), (8, 2));
+        assert!(c.pixels[0..4]
+            .iter()
+            .zip(&left.pixels[0..4])
+            .all(|(p, q)| p.approx_eq(*q)));
+        assert!(c.pixels[6].approx_eq(Color(0.2, 0.4, 0.6)));
+        assert!(c.pixels[8].approx_eq(left.pixels[4]));
+
+        let c = Canvas::anaglyph(&left, &right);
+        assert_eq!((c.width, c.height), (4, 2));
+        assert!(c.pixels[0].approx_eq(Color::BLACK));
+        assert!(c.pixels[2].approx_eq(Color(0.0, 0.4, 0.6)));
+        assert!(c.pixels[3].approx_eq(Color(1.0, 0.0, 0.0)));
+    }
+
     #[test]
     fn canvas_write_to_ppm() -> io::Result<()> {
         // write PPM to vector
@@ -153,4 +292,35 @@ mod tests {
         assert_eq!(v, want);
         Ok(())
     }
+
+    #[test]
+    fn canvas_write_to_png() -> io::Result<()> {
+        let c = sample_canvas();
+        let mut v: Vec<u8> = Vec::new();
+        c.write_to_png(&mut v)?;
+
+        let mut want = b"\x89PNG\r\n\x1a\n".to_vec();
+        // IHDR: 4x2 pixels, RGB with 8 bits per channel
+        want.extend_from_slice(&[0, 0, 0, 13]);
+        want.extend_from_slice(b"IHDR");
+        want.extend_from_slice(&[0, 0, 0, 4, 0, 0, 0, 2, 8, 2, 0, 0, 0]);
+        want.extend_from_slice(&[0xf0, 0xca, 0xea, 0x34]);
+        // IDAT: zlib header, one stored block with 26 bytes, the rows with filter type 0
+        let raw = [
+            0, 0, 0, 0xff, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0xff, // first row
+            0, 0xff, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0, // second row
+        ];
+        want.extend_from_slice(&[0, 0, 0, 37]);
+        want.extend_from_slice(b"IDAT");
+        want.extend_from_slice(&[0x78, 0x01, 1, 26, 0, 0xe5, 0xff]);
+        want.extend_from_slice(&raw);
+        want.extend_from_slice(&[0x51, 0xc8, 0x05, 0xfb]); // Adler-32 of the raw data
+        assert_eq!(&v[..want.len()], &want[..]);
+        // skip the CRC of the IDAT chunk; IEND is always the same
+        assert_eq!(
+            &v[want.len() + 4..],
+            &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]
+        );
+        Ok(())
+    }
 }
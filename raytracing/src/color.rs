@@ -1,7 +1,7 @@
 //! Defines a type for color values and some operations on it.
 
 use crate::approx::ApproxEq;
-use std::ops::{Add, AddAssign, Mul};
+use std::ops::{Add, AddAssign, Mul, Sub};
 
 /// A color with red, green, and blue values. The values should be between 0 and 1; other values
 /// will be clamped to the [0, 1] range.
@@ -41,6 +41,14 @@ impl AddAssign<Color> for Color {
     }
 }
 
+impl Sub<Color> for Color {
+    type Output = Color;
+
+    fn sub(self, other: Color) -> Color {
+        Color(self.0 - other.0, self.1 - other.1, self.2 - other.2)
+    }
+}
+
 impl Mul<Color> for f64 {
     type Output = Color;
 
@@ -59,6 +67,15 @@ impl Mul<f64> for Color {
     }
 }
 
+impl Mul<Color> for Color {
+    type Output = Color;
+
+    /// Multiplies the colors component-wise, e.g. for light filtered by a colored surface.
+    fn mul(self, other: Color) -> Color {
+        Color(self.0 * other.0, self.1 * other.1, self.2 * other.2)
+    }
+}
+
 fn to_u8(f: f64) -> u8 {
     (256.0 * f) as u8
 }
@@ -110,5 +127,7 @@ mod tests {
         assert!((2.0 * c).approx_eq(Color(0.2, 0.4, 0.6)));
         assert!((c * 2.0).approx_eq(Color(0.2, 0.4, 0.6)));
         assert!((c + d).approx_eq(Color(0.3, 0.5, 0.7)));
+        assert!((d - c).approx_eq(Color(0.1, 0.1, 0.1)));
+        assert!((c * d).approx_eq(Color(0.02, 0.06, 0.12)));
     }
 }
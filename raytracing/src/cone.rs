@@ -0,0 +1,246 @@
+//! Defines a cone primitive.
+
+use crate::aabb::Aabb;
+use crate::disk::{intersect_disk, Disk};
+use crate::ray::Ray;
+use crate::scene::solve_quadratic;
+use crate::vec3::Vec3;
+use std::f64::consts::PI;
+
+/// A cone around the y axis, from `y = 0` to `y = height`, with the given radius at the bottom
+/// and the top.
+///
+/// If one of the radii is zero, it's a pointed cone; otherwise it's a truncated cone, e.g. a lamp
+/// shade. If `capped` is true, the ends are closed by disks (except where the radius is zero).
+#[derive(Debug, Clone, Copy)]
+pub struct Cone {
+    pub bottom_radius: f64,
+    pub top_radius: f64,
+    pub height: f64,
+    pub capped: bool,
+}
+
+impl Cone {
+    /// Return the values `t` where the ray intersects the cone.
+    ///
+    /// Returns 0 to 2 values in a sorted vector.
+    pub fn intersect_ray(&self, ray: Ray) -> Vec<f64> {
+        let Ray {
+            origin: o,
+            direction: d,
+            ..
+        } = ray;
+
+        // the side is the part of the surface x^2 + z^2 = (r0 + k*y)^2 between the caps
+        let r0 = self.bottom_radius;
+        let k = self.slope();
+        let r_o = r0 + k * o.y;
+        let a = d.x * d.x + d.z * d.z - k * k * d.y * d.y;
+        let b = 2.0 * (o.x * d.x + o.z * d.z - k * d.y * r_o);
+        let c = o.x * o.x + o.z * o.z - r_o * r_o;
+        let mut ts = solve_quadratic(a, b, c);
+        ts.retain(|&t| (0.0..=self.height).contains(&ray.at(t).y));
+
+        if self.capped {
+            for (y, r) in [(0.0, self.bottom_radius), (self.height, self.top_radius)] {
+                if r > 0.0 {
+                    ts.extend(intersect_disk(ray, y, r));
+                }
+            }
+            ts.sort_by(f64::total_cmp);
+            ts.dedup_by(|a, b| (*a - *b).abs() < 1E-9);
+        }
+        ts
+    }
+
+    /// Returns the surface normal at point `p`, which must be on the surface of the cone.
+    ///
+    /// The side has no normal at the tip of a pointed cone, so there the normal points along the
+    /// axis, away from the cone.
+    pub fn normal_at(&self, p: Vec3) -> Vec3 {
+        let side = Vec3::new(p.x, -self.slope() * self.radius_at(p.y), p.z);
+        if !self.on_cap(p) && side.len() > 1E-9 {
+            side
+        } else if p.y < 0.5 * self.height {
+            Vec3::new(0.0, -1.0, 0.0)
+        } else {
+            Vec3::new(0.0, 1.0, 0.0)
+        }
+    }
+
+    /// Returns the texture coordinates at point `p`, which must be on the surface of the cone.
+    ///
+    /// On the side, `u` goes around the cone, starting at the back, and `v` goes from the bottom to
+    /// the top. The caps are mapped like disks.
+    pub fn uv_at(&self, p: Vec3) -> (f64, f64) {
+        if !self.on_cap(p) {
+            (0.5 + p.x.atan2(-p.z) / (2.0 * PI), p.y / self.height)
+        } else if p.y < 0.5 * self.height {
+            Disk {
+                radius: self.bottom_radius,
+            }
+            .uv_at(p)
+        } else {
+            Disk {
+                radius: self.top_radius,
+            }
+            .uv_at(p)
+        }
+    }
+
+    /// Returns the direction in which `u` increases at point `p`, which must be on the surface of
+    /// the cone.
+    pub fn tangent_at(&self, p: Vec3) -> Vec3 {
+        if self.on_cap(p) {
+            Vec3::new(1.0, 0.0, 0.0)
+        } else {
+            Vec3::new(-p.z, 0.0, p.x)
+        }
+    }
+
+    /// Returns true if point `p` on the surface is on one of the caps rather than the side.
+    fn on_cap(&self, p: Vec3) -> bool {
+        if !self.capped {
+            return false;
+        }
+        let k = self.slope();
+        let r = self.radius_at(p.y);
+        let to_side = ((p.x * p.x + p.z * p.z).sqrt() - r).abs() / (1.0 + k * k).sqrt();
+        let to_cap = p.y.abs().min((self.height - p.y).abs());
+        to_cap < to_side
+    }
+
+    /// Returns a box that contains the cone.
+    pub fn bounds(&self) -> Aabb {
+        let r = self.bottom_radius.max(self.top_radius);
+        Aabb {
+            min: Vec3::new(-r, 0.0, -r),
+            max: Vec3::new(r, self.height, r),
+        }
+    }
+
+    /// Returns how much the radius changes per unit of height.
+    fn slope(&self) -> f64 {
+        (self.top_radius - self.bottom_radius) / self.height
+    }
+
+    fn radius_at(&self, y: f64) -> f64 {
+        self.bottom_radius + self.slope() * y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+
+    // a pointed cone with its tip at y = 2
+    fn cone(capped: bool) -> Cone {
+        Cone {
+            bottom_radius: 1.0,
+            top_radius: 0.0,
+            height: 2.0,
+            capped,
+        }
+    }
+
+    #[test]
+    fn cone_intersect_ray() {
+        // horizontally at half height, where the radius is 0.5
+        let ray = Ray {
+            origin: Vec3::new(-5.0, 1.0, 0.0),
+            direction: Vec3::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        assert!(cone(false).intersect_ray(ray).approx_eq(&vec![4.5, 5.5]));
+
+        // along the axis, through the tip and the bottom cap
+        let ray = Ray {
+            origin: Vec3::new(0.0, 5.0, 0.0),
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        assert!(cone(false).intersect_ray(ray).approx_eq(&vec![3.0]));
+        assert!(cone(true).intersect_ray(ray).approx_eq(&vec![3.0, 5.0]));
+
+        // parallel to the side of the cone, so the quadratic equation degenerates
+        let ray = Ray {
+            origin: Vec3::new(-2.0, 0.0, 0.0),
+            direction: Vec3::new(1.0, -2.0, 0.0),
+            time: 0.0,
+        };
+        let ts = cone(true).intersect_ray(ray);
+        assert!(ts.is_empty(), "got {:?}", ts);
+        let ray = Ray {
+            origin: Vec3::new(0.5, 0.0, 0.0),
+            direction: Vec3::new(1.0, 2.0, 0.0),
+            time: 0.0,
+        };
+        assert!(cone(false).intersect_ray(ray).approx_eq(&vec![0.25]));
+
+        // a truncated cone, wider at the top
+        let shade = Cone {
+            bottom_radius: 1.0,
+            top_radius: 2.0,
+            height: 1.0,
+            capped: false,
+        };
+        let ray = Ray {
+            origin: Vec3::new(-5.0, 0.5, 0.0),
+            direction: Vec3::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        assert!(shade.intersect_ray(ray).approx_eq(&vec![3.5, 6.5]));
+
+        // through the rim of the top cap, where the side and the cap are hit at almost the same t,
+        // which only counts once
+        let shade = Cone {
+            capped: true,
+            ..shade
+        };
+        let origin = Vec3::new(-0.3, 3.1, 0.2);
+        for i in 0..50 {
+            let angle = 0.0137 * i as f64;
+            let rim = Vec3::new(2.0 * angle.cos(), 1.0, 2.0 * angle.sin());
+            let ray = Ray {
+                origin,
+                direction: rim - origin,
+                time: 0.0,
+            };
+            let ts = shade.intersect_ray(ray);
+            assert!(ts.windows(2).all(|w| w[1] - w[0] > 1E-9), "got {:?}", ts);
+        }
+    }
+
+    #[test]
+    fn cone_normal_at() {
+        let c = cone(true);
+        let n = c.normal_at(Vec3::new(0.5, 1.0, 0.0)).normalized();
+        assert!(n.approx_eq(Vec3::new(2.0, 1.0, 0.0).normalized()));
+        let n = c.normal_at(Vec3::new(0.0, 1.0, -0.5)).normalized();
+        assert!(n.approx_eq(Vec3::new(0.0, 1.0, -2.0).normalized()));
+        let n = c.normal_at(Vec3::new(0.2, 0.0, 0.1));
+        assert!(n.approx_eq(Vec3::new(0.0, -1.0, 0.0)));
+
+        // at the tip, the normal points along the axis
+        for capped in [false, true] {
+            let n = cone(capped).normal_at(Vec3::new(0.0, 2.0, 0.0));
+            assert!(n.approx_eq(Vec3::new(0.0, 1.0, 0.0)), "got {:?}", n);
+            let upside_down = Cone {
+                bottom_radius: 0.0,
+                top_radius: 1.0,
+                ..cone(capped)
+            };
+            let n = upside_down.normal_at(Vec3::new(0.0, 0.0, 0.0));
+            assert!(n.approx_eq(Vec3::new(0.0, -1.0, 0.0)), "got {:?}", n);
+        }
+    }
+
+    #[test]
+    fn cone_uv_at() {
+        let c = cone(true);
+        assert!(c.uv_at(Vec3::new(0.0, 1.0, -0.5)).approx_eq((0.5, 0.5)));
+        assert!(c.uv_at(Vec3::new(-0.75, 0.5, 0.0)).approx_eq((0.25, 0.25)));
+        assert!(c.uv_at(Vec3::new(0.5, 0.0, 0.0)).approx_eq((0.75, 0.5)));
+    }
+}
@@ -0,0 +1,263 @@
+//! Defines constructive solid geometry: shapes built by combining two objects.
+
+use crate::aabb::Aabb;
+use crate::ray::Ray;
+use crate::scene::{Hit, Object};
+
+/// How the two objects in a CSG shape are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsgOperation {
+    /// Points that are in either object.
+    Union,
+
+    /// Points that are in both objects.
+    Intersection,
+
+    /// Points that are in the left object but not in the right one.
+    Difference,
+}
+
+impl CsgOperation {
+    /// Returns true if a point is in the combined shape, given whether it's in each of the objects.
+    fn contains(self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOperation::Union => in_left || in_right,
+            CsgOperation::Intersection => in_left && in_right,
+            CsgOperation::Difference => in_left && !in_right,
+        }
+    }
+}
+
+/// A shape built by combining two objects with a set operation.
+///
+/// The objects must be closed shapes, so that the points where a ray hits them alternate between
+/// entering and leaving. Each object keeps its own transformation (relative to the CSG shape) and
+/// its own material, so for example the holes carved out of a shape can have a different color;
+/// the material of the object that contains the CSG shape isn't used.
+#[derive(Debug, Clone)]
+pub struct Csg {
+    pub operation: CsgOperation,
+    pub left: Box<Object>,
+    pub right: Box<Object>,
+}
+
+impl Csg {
+    /// Returns all points where the line through the ray hits the shape, sorted by `t`.
+    ///
+    /// The hits on each object describe the intervals along the line that are inside it; this
+    /// combines the intervals and keeps the hits where the line enters or leaves the result.
+    /// Normals on surfaces of the right object that bound a difference are flipped, since they
+    /// face into the remaining shape.
+    pub fn hits(&self, ray: Ray) -> Vec<Hit<'_>> {
+        let left = closed_hits(&self.left, ray);
+        let right = closed_hits(&self.right, ray);
+
+        let mut result = vec![];
+        let (mut i, mut j) = (0, 0);
+        let (mut in_left, mut in_right) = (false, false);
+        while i < left.len() || j < right.len() {
+            let inside_before = self.operation.contains(in_left, in_right);
+            let from_left = j >= right.len() || (i < left.len() && left[i].t <= right[j].t);
+            let mut hit = if from_left {
+                in_left = !in_left;
+                i += 1;
+                left[i - 1]
+            } else {
+                in_right = !in_right;
+                j += 1;
+                right[j - 1]
+            };
+            if inside_before != self.operation.contains(in_left, in_right) {
+                if !from_left && self.operation == CsgOperation::Difference {
+                    hit.normal = -hit.normal;
+                }
+                result.push(hit);
+            }
+        }
+        result
+    }
+
+    /// Returns a box that contains the shape.
+    pub fn bounds(&self) -> Aabb {
+        match self.operation {
+            CsgOperation::Union => self.left.bounds().union(self.right.bounds()),
+            CsgOperation::Intersection | CsgOperation::Difference => self.left.bounds(),
+        }
+    }
+}
+
+/// Returns the hits on an object, dropping the last one if there's an odd number of hits (e.g.
+/// when the ray only touches a sphere), so the hits always come in entering/leaving pairs.
+fn closed_hits(object: &Object, ray: Ray) -> Vec<Hit<'_>> {
+    let mut hits = object.hits(ray);
+    if hits.len() % 2 == 1 {
+        hits.pop();
+    }
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+    use crate::color::Color;
+    use crate::cuboid::Cuboid;
+    use crate::scene::{Material, Shape, Sphere};
+    use crate::transform::Transform;
+    use crate::vec3::Vec3;
+
+    // two unit spheres, overlapping between z = 1 and z = 2
+    fn csg(operation: CsgOperation) -> Csg {
+        let sphere = |z, color| Object {
+            shape: Shape::Sphere(Sphere {
+                center: Vec3::new(0.0, 0.0, z),
+                radius: 1.0,
+            }),
+            material: Material {
+                color,
+                ..Material::BLACK
+            },
+            transform: Transform::IDENTITY,
+            motion: None,
+        };
+        Csg {
+            operation,
+            left: Box::new(sphere(1.0, Color(1.0, 0.0, 0.0))),
+            right: Box::new(sphere(2.0, Color(0.0, 1.0, 0.0))),
+        }
+    }
+
+    fn ray() -> Ray {
+        Ray {
+            origin: Vec3::new(0.0, 0.0, -5.0),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        }
+    }
+
+    fn ts(hits: &[Hit]) -> Vec<f64> {
+        hits.iter().map(|hit| hit.t).collect()
+    }
+
+    #[test]
+    fn csg_union() {
+        let csg = csg(CsgOperation::Union);
+        let hits = csg.hits(ray());
+        assert!(ts(&hits).approx_eq(&vec![5.0, 8.0]));
+        assert!(hits[0].normal.approx_eq(Vec3::new(0.0, 0.0, -1.0)));
+        assert!(hits[0].material.color.approx_eq(Color(1.0, 0.0, 0.0)));
+        assert!(hits[1].normal.approx_eq(Vec3::new(0.0, 0.0, 1.0)));
+        assert!(hits[1].material.color.approx_eq(Color(0.0, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn csg_intersection() {
+        let csg = csg(CsgOperation::Intersection);
+        let hits = csg.hits(ray());
+        assert!(ts(&hits).approx_eq(&vec![6.0, 7.0]));
+        assert!(hits[0].normal.approx_eq(Vec3::new(0.0, 0.0, -1.0)));
+        assert!(hits[0].material.color.approx_eq(Color(0.0, 1.0, 0.0)));
+        assert!(hits[1].normal.approx_eq(Vec3::new(0.0, 0.0, 1.0)));
+        assert!(hits[1].material.color.approx_eq(Color(1.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn csg_difference() {
+        let csg = csg(CsgOperation::Difference);
+        let hits = csg.hits(ray());
+        assert!(ts(&hits).approx_eq(&vec![5.0, 6.0]));
+        assert!(hits[0].normal.approx_eq(Vec3::new(0.0, 0.0, -1.0)));
+        // the surface of the subtracted sphere, facing out of the remaining shape
+        assert!(hits[1].normal.approx_eq(Vec3::new(0.0, 0.0, 1.0)));
+        assert!(hits[1].material.color.approx_eq(Color(0.0, 1.0, 0.0)));
+
+        // a ray that only hits the right sphere hits nothing
+        let ray = Ray {
+            origin: Vec3::new(0.0, 0.0, 2.5),
+            direction: Vec3::new(0.0, 1.0, 0.0),
+            time: 0.0,
+        };
+        assert!(csg.hits(ray).is_empty());
+
+        // a ray that only touches the left sphere is ignored
+        let ray = Ray {
+            origin: Vec3::new(1.0, 0.0, -5.0),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        assert!(csg.hits(ray).is_empty());
+    }
+
+    #[test]
+    fn csg_nested() {
+        // subtract a third sphere from the union of the first two, leaving a hole in the middle
+        let union = Object {
+            shape: Shape::Csg(csg(CsgOperation::Union)),
+            material: Material::BLACK,
+            transform: Transform::IDENTITY,
+            motion: None,
+        };
+        let hole = Object {
+            shape: Shape::Sphere(Sphere {
+                center: Vec3::new(0.0, 0.0, 1.5),
+                radius: 0.25,
+            }),
+            material: Material::BLACK,
+            transform: Transform::IDENTITY,
+            motion: None,
+        };
+        let csg = Csg {
+            operation: CsgOperation::Difference,
+            left: Box::new(union),
+            right: Box::new(hole),
+        };
+        let hits = csg.hits(ray());
+        assert!(ts(&hits).approx_eq(&vec![5.0, 6.25, 6.75, 8.0]));
+        assert!(hits[1].normal.approx_eq(Vec3::new(0.0, 0.0, 1.0)));
+        assert!(hits[2].normal.approx_eq(Vec3::new(0.0, 0.0, -1.0)));
+
+        let bounds = csg.bounds();
+        assert!(bounds.min.approx_eq(Vec3::new(-1.0, -1.0, 0.0)));
+        assert!(bounds.max.approx_eq(Vec3::new(1.0, 1.0, 3.0)));
+    }
+
+    #[test]
+    fn csg_rounded_cube() {
+        // a cube with its corners cut off by a sphere, like a die
+        let object = |shape| Object {
+            shape,
+            material: Material::BLACK,
+            transform: Transform::IDENTITY,
+            motion: None,
+        };
+        let csg = Csg {
+            operation: CsgOperation::Intersection,
+            left: Box::new(object(Shape::Cuboid(Cuboid::centered(Vec3::new(
+                2.0, 2.0, 2.0,
+            ))))),
+            right: Box::new(object(Shape::Sphere(Sphere {
+                center: Vec3::new(0.0, 0.0, 0.0),
+                radius: 1.3,
+            }))),
+        };
+
+        // through the middle of a face, the cube is hit
+        let hits = csg.hits(ray());
+        assert!(ts(&hits).approx_eq(&vec![4.0, 6.0]));
+        assert!(hits[0].normal.approx_eq(Vec3::new(0.0, 0.0, -1.0)));
+
+        // near a corner, the sphere is hit
+        let ray = Ray {
+            origin: Vec3::new(0.9, 0.9, -5.0),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let hits = csg.hits(ray);
+        assert_eq!(hits.len(), 2);
+        let z = (1.3f64 * 1.3 - 0.9 * 0.9 - 0.9 * 0.9).sqrt();
+        assert!(hits[0].t.approx_eq(5.0 - z));
+        assert!(hits[0]
+            .normal
+            .approx_eq(Vec3::new(0.9, 0.9, -z).normalized()));
+    }
+}
@@ -0,0 +1,228 @@
+//! Defines a box-shaped primitive.
+
+use crate::aabb::Aabb;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// An axis-aligned box, defined by its minimum and maximum corners.
+///
+/// For an oriented box, give the object containing the cuboid a rotation transform.
+#[derive(Debug, Clone, Copy)]
+pub struct Cuboid {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Cuboid {
+    /// Returns a cuboid centered on the origin with the given size along each axis.
+    pub fn centered(size: Vec3) -> Cuboid {
+        Cuboid {
+            min: -0.5 * size,
+            max: 0.5 * size,
+        }
+    }
+
+    /// Return the values `t` where the ray intersects the cuboid.
+    ///
+    /// Returns 0 or 2 values in a sorted vector: where the ray enters and where it leaves the box.
+    pub fn intersect_ray(&self, ray: Ray) -> Vec<f64> {
+        match self.bounds().intersect_ray(ray) {
+            Some((t0, t1)) => vec![t0, t1],
+            None => vec![],
+        }
+    }
+
+    /// Returns the surface normal at point `p`, which must be on the surface of the cuboid.
+    ///
+    /// This is the normal of the face closest to `p`.
+    pub fn normal_at(&self, p: Vec3) -> Vec3 {
+        let faces = [
+            (p.x - self.min.x, Vec3::new(-1.0, 0.0, 0.0)),
+            (self.max.x - p.x, Vec3::new(1.0, 0.0, 0.0)),
+            (p.y - self.min.y, Vec3::new(0.0, -1.0, 0.0)),
+            (self.max.y - p.y, Vec3::new(0.0, 1.0, 0.0)),
+            (p.z - self.min.z, Vec3::new(0.0, 0.0, -1.0)),
+            (self.max.z - p.z, Vec3::new(0.0, 0.0, 1.0)),
+        ];
+        faces
+            .into_iter()
+            .min_by(|(a, _), (b, _)| a.abs().total_cmp(&b.abs()))
+            .map(|(_, n)| n)
+            .unwrap()
+    }
+
+    /// Returns the texture coordinates at point `p`, which must be on the surface of the cuboid.
+    ///
+    /// Each face is mapped to the whole texture, the right way up when seen from outside; the top
+    /// and bottom faces have `v` along the z axis.
+    pub fn uv_at(&self, p: Vec3) -> (f64, f64) {
+        box_mapping(self.bounds(), p, self.normal_at(p)).0
+    }
+
+    /// Returns the direction in which `u` increases at point `p`, which must be on the surface of
+    /// the cuboid.
+    pub fn tangent_at(&self, p: Vec3) -> Vec3 {
+        box_mapping(self.bounds(), p, self.normal_at(p)).1
+    }
+
+    /// Returns a box that contains the cuboid.
+    pub fn bounds(&self) -> Aabb {
+        Aabb {
+            min: self.min,
+            max: self.max,
+        }
+    }
+}
+
+/// Returns texture coordinates for point `p` with normal `n` by projecting it onto the face of the
+/// box that the normal points to most, and the direction in which `u` increases on that face.
+/// Also used for shapes defined by distance functions.
+pub(crate) fn box_mapping(bounds: Aabb, p: Vec3, n: Vec3) -> ((f64, f64), Vec3) {
+    let fraction = |x: f64, min: f64, max: f64| {
+        if max > min {
+            (x - min) / (max - min)
+        } else {
+            0.5
+        }
+    };
+    let fx = fraction(p.x, bounds.min.x, bounds.max.x);
+    let fy = fraction(p.y, bounds.min.y, bounds.max.y);
+    let fz = fraction(p.z, bounds.min.z, bounds.max.z);
+    let (ax, ay, az) = (n.x.abs(), n.y.abs(), n.z.abs());
+    if ax >= ay && ax >= az {
+        if n.x > 0.0 {
+            ((fz, fy), Vec3::new(0.0, 0.0, 1.0))
+        } else {
+            ((1.0 - fz, fy), Vec3::new(0.0, 0.0, -1.0))
+        }
+    } else if ay >= az {
+        if n.y > 0.0 {
+            ((fx, fz), Vec3::new(1.0, 0.0, 0.0))
+        } else {
+            ((1.0 - fx, fz), Vec3::new(-1.0, 0.0, 0.0))
+        }
+    } else if n.z > 0.0 {
+        ((1.0 - fx, fy), Vec3::new(-1.0, 0.0, 0.0))
+    } else {
+        ((fx, fy), Vec3::new(1.0, 0.0, 0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+    use crate::color::Color;
+    use crate::scene::{Material, Object, Shape};
+    use crate::transform::Transform;
+    use std::f64::consts::FRAC_PI_4;
+
+    #[test]
+    fn cuboid_intersect_ray() {
+        let cuboid = Cuboid {
+            min: Vec3::new(-1.0, -2.0, 3.0),
+            max: Vec3::new(1.0, 2.0, 5.0),
+        };
+        let ray = Ray {
+            origin: Vec3::new(0.0, 0.0, 0.0),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        assert!(cuboid.intersect_ray(ray).approx_eq(&vec![3.0, 5.0]));
+
+        // through the side faces
+        let ray = Ray {
+            origin: Vec3::new(-3.0, 0.0, 4.0),
+            direction: Vec3::new(1.0, 0.5, 0.0),
+            time: 0.0,
+        };
+        assert!(cuboid.intersect_ray(ray).approx_eq(&vec![2.0, 4.0]));
+
+        // missing the box
+        let ray = Ray {
+            origin: Vec3::new(0.0, 3.0, 0.0),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        assert!(cuboid.intersect_ray(ray).is_empty());
+    }
+
+    #[test]
+    fn cuboid_normal_at() {
+        let cuboid = Cuboid::centered(Vec3::new(2.0, 4.0, 6.0));
+        let cases = [
+            (Vec3::new(-1.0, 0.5, 0.5), Vec3::new(-1.0, 0.0, 0.0)),
+            (Vec3::new(1.0, -1.5, 2.0), Vec3::new(1.0, 0.0, 0.0)),
+            (Vec3::new(0.5, -2.0, 0.5), Vec3::new(0.0, -1.0, 0.0)),
+            (Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+            (Vec3::new(0.9, 1.9, -3.0), Vec3::new(0.0, 0.0, -1.0)),
+            (Vec3::new(0.0, 0.0, 3.0), Vec3::new(0.0, 0.0, 1.0)),
+        ];
+        for (p, want) in cases {
+            let got = cuboid.normal_at(p);
+            assert!(got.approx_eq(want), "normal_at({:?}) == {:?}", p, got);
+        }
+    }
+
+    #[test]
+    fn cuboid_uv_at() {
+        let cuboid = Cuboid::centered(Vec3::new(2.0, 4.0, 6.0));
+        let x = Vec3::new(1.0, 0.0, 0.0);
+        let z = Vec3::new(0.0, 0.0, 1.0);
+        let cases = [
+            (Vec3::new(0.5, 1.0, -3.0), (0.75, 0.75), x),   // front
+            (Vec3::new(0.5, 1.0, 3.0), (0.25, 0.75), -x),   // back
+            (Vec3::new(1.0, -1.0, 1.5), (0.75, 0.25), z),   // right
+            (Vec3::new(-1.0, -1.0, 1.5), (0.25, 0.25), -z), // left
+            (Vec3::new(0.5, 2.0, -1.5), (0.75, 0.25), x),   // top
+            (Vec3::new(0.5, -2.0, -1.5), (0.25, 0.25), -x), // bottom
+        ];
+        for (p, want_uv, want_tangent) in cases {
+            let got = cuboid.uv_at(p);
+            assert!(got.approx_eq(want_uv), "uv_at({:?}) == {:?}", p, got);
+            let got = cuboid.tangent_at(p);
+            assert!(
+                got.approx_eq(want_tangent),
+                "tangent_at({:?}) == {:?}",
+                p,
+                got
+            );
+        }
+    }
+
+    #[test]
+    fn oriented_cuboid() {
+        // a unit cube rotated by 45 degrees around the y axis
+        let object = Object {
+            shape: Shape::Cuboid(Cuboid::centered(Vec3::new(1.0, 1.0, 1.0))),
+            material: Material {
+                color: Color::WHITE,
+                ..Material::BLACK
+            },
+            transform: Transform::translation(Vec3::new(0.0, 0.0, 5.0))
+                * Transform::rotation(Vec3::new(0.0, 1.0, 0.0), FRAC_PI_4),
+            motion: None,
+        };
+        let ray = Ray {
+            origin: Vec3::new(0.0, 0.0, 0.0),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let hits = object.hits(ray);
+        let d = 0.5 * 2f64.sqrt();
+        assert_eq!(hits.len(), 2);
+        assert!(hits[0].t.approx_eq(5.0 - d));
+        assert!(hits[1].t.approx_eq(5.0 + d));
+
+        // hit a face head-on
+        let ray = Ray {
+            origin: Vec3::new(-5.0, 0.0, 0.0),
+            direction: Vec3::new(1.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let hits = object.hits(ray);
+        assert_eq!(hits.len(), 2);
+        let want = Vec3::new(-1.0, 0.0, -1.0).normalized();
+        assert!(hits[0].normal.approx_eq(want));
+    }
+}
@@ -0,0 +1,207 @@
+//! Defines a cylinder primitive.
+
+use crate::aabb::Aabb;
+use crate::disk::{intersect_disk, Disk};
+use crate::ray::Ray;
+use crate::scene::solve_quadratic;
+use crate::vec3::Vec3;
+use std::f64::consts::PI;
+
+/// A cylinder around the y axis, from `y = 0` to `y = height`.
+///
+/// If `capped` is true, the cylinder is closed at both ends by disks; otherwise it's an open tube.
+#[derive(Debug, Clone, Copy)]
+pub struct Cylinder {
+    pub radius: f64,
+    pub height: f64,
+    pub capped: bool,
+}
+
+impl Cylinder {
+    /// Return the values `t` where the ray intersects the cylinder.
+    ///
+    /// Returns 0 to 2 values in a sorted vector.
+    pub fn intersect_ray(&self, ray: Ray) -> Vec<f64> {
+        let Ray {
+            origin: o,
+            direction: d,
+            ..
+        } = ray;
+        let r = self.radius;
+
+        // the side is the part of the infinite cylinder x^2 + z^2 = r^2 between the caps
+        let a = d.x * d.x + d.z * d.z;
+        let b = 2.0 * (o.x * d.x + o.z * d.z);
+        let c = o.x * o.x + o.z * o.z - r * r;
+        let mut ts = solve_quadratic(a, b, c);
+        ts.retain(|&t| (0.0..=self.height).contains(&ray.at(t).y));
+
+        if self.capped {
+            ts.extend(intersect_disk(ray, 0.0, r));
+            ts.extend(intersect_disk(ray, self.height, r));
+            ts.sort_by(f64::total_cmp);
+            ts.dedup_by(|a, b| (*a - *b).abs() < 1E-9);
+        }
+        ts
+    }
+
+    /// Returns the surface normal at point `p`, which must be on the surface of the cylinder.
+    pub fn normal_at(&self, p: Vec3) -> Vec3 {
+        if !self.on_cap(p) {
+            Vec3::new(p.x, 0.0, p.z) / self.radius
+        } else if p.y < 0.5 * self.height {
+            Vec3::new(0.0, -1.0, 0.0)
+        } else {
+            Vec3::new(0.0, 1.0, 0.0)
+        }
+    }
+
+    /// Returns the texture coordinates at point `p`, which must be on the surface of the cylinder.
+    ///
+    /// On the side, `u` goes around the cylinder, starting at the back, and `v` goes from the
+    /// bottom to the top. The caps are mapped like disks.
+    pub fn uv_at(&self, p: Vec3) -> (f64, f64) {
+        if self.on_cap(p) {
+            Disk {
+                radius: self.radius,
+            }
+            .uv_at(p)
+        } else {
+            (0.5 + p.x.atan2(-p.z) / (2.0 * PI), p.y / self.height)
+        }
+    }
+
+    /// Returns the direction in which `u` increases at point `p`, which must be on the surface of
+    /// the cylinder.
+    pub fn tangent_at(&self, p: Vec3) -> Vec3 {
+        if self.on_cap(p) {
+            Vec3::new(1.0, 0.0, 0.0)
+        } else {
+            Vec3::new(-p.z, 0.0, p.x)
+        }
+    }
+
+    /// Returns true if point `p` on the surface is on one of the caps rather than the side.
+    fn on_cap(&self, p: Vec3) -> bool {
+        if !self.capped {
+            return false;
+        }
+        let to_side = ((p.x * p.x + p.z * p.z).sqrt() - self.radius).abs();
+        let to_cap = p.y.abs().min((self.height - p.y).abs());
+        to_cap < to_side
+    }
+
+    /// Returns a box that contains the cylinder.
+    pub fn bounds(&self) -> Aabb {
+        let r = self.radius;
+        Aabb {
+            min: Vec3::new(-r, 0.0, -r),
+            max: Vec3::new(r, self.height, r),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+
+    fn cylinder(capped: bool) -> Cylinder {
+        Cylinder {
+            radius: 1.0,
+            height: 2.0,
+            capped,
+        }
+    }
+
+    #[test]
+    fn cylinder_intersect_ray() {
+        // through the side
+        let ray = Ray {
+            origin: Vec3::new(-5.0, 1.0, 0.0),
+            direction: Vec3::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        assert!(cylinder(false)
+            .intersect_ray(ray)
+            .approx_eq(&vec![4.0, 6.0]));
+        assert!(cylinder(true).intersect_ray(ray).approx_eq(&vec![4.0, 6.0]));
+
+        // above the cylinder
+        let ray = Ray {
+            origin: Vec3::new(-5.0, 3.0, 0.0),
+            direction: Vec3::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        assert!(cylinder(true).intersect_ray(ray).is_empty());
+
+        // along the axis: only the caps are hit
+        let ray = Ray {
+            origin: Vec3::new(0.5, 5.0, 0.0),
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        assert!(cylinder(false).intersect_ray(ray).is_empty());
+        assert!(cylinder(true).intersect_ray(ray).approx_eq(&vec![3.0, 5.0]));
+
+        // diagonally, through the top cap and the side
+        let ray = Ray {
+            origin: Vec3::new(0.0, 3.0, 0.0),
+            direction: Vec3::new(0.5, -1.0, 0.0),
+            time: 0.0,
+        };
+        assert!(cylinder(false).intersect_ray(ray).approx_eq(&vec![2.0]));
+        assert!(cylinder(true).intersect_ray(ray).approx_eq(&vec![1.0, 2.0]));
+
+        // through the rim of the top cap, where the side and the cap are hit at almost the same t,
+        // which only counts once
+        let origin = Vec3::new(-0.3, 3.1, 0.2);
+        for i in 0..50 {
+            let angle = 0.0137 * i as f64;
+            let rim = Vec3::new(angle.cos(), 2.0, angle.sin());
+            let ray = Ray {
+                origin,
+                direction: rim - origin,
+                time: 0.0,
+            };
+            let ts = cylinder(true).intersect_ray(ray);
+            assert!(ts.windows(2).all(|w| w[1] - w[0] > 1E-9), "got {:?}", ts);
+        }
+    }
+
+    #[test]
+    fn cylinder_normal_at() {
+        let c = cylinder(true);
+        let cases = [
+            (Vec3::new(1.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0)),
+            (Vec3::new(0.0, 0.5, -1.0), Vec3::new(0.0, 0.0, -1.0)),
+            (Vec3::new(0.5, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0)),
+            (Vec3::new(0.0, 2.0, 0.5), Vec3::new(0.0, 1.0, 0.0)),
+        ];
+        for (p, want) in cases {
+            let got = c.normal_at(p);
+            assert!(got.approx_eq(want), "normal_at({:?}) == {:?}", p, got);
+        }
+
+        // uncapped cylinders only have the side
+        let p = Vec3::new(0.6, 0.0, 0.8);
+        assert!(cylinder(false)
+            .normal_at(p)
+            .approx_eq(Vec3::new(0.6, 0.0, 0.8)));
+    }
+
+    #[test]
+    fn cylinder_uv_at() {
+        let c = cylinder(true);
+        let cases = [
+            (Vec3::new(0.0, 1.0, -1.0), (0.5, 0.5)),
+            (Vec3::new(1.0, 0.5, 0.0), (0.75, 0.25)),
+            (Vec3::new(-1.0, 2.0, 0.0), (0.25, 1.0)),
+            (Vec3::new(0.5, 2.0, -0.5), (0.75, 0.25)),
+        ];
+        for (p, want) in cases {
+            let got = c.uv_at(p);
+            assert!(got.approx_eq(want), "uv_at({:?}) == {:?}", p, got);
+        }
+    }
+}
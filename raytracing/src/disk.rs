@@ -0,0 +1,110 @@
+//! Defines a flat, circular primitive.
+
+use crate::aabb::Aabb;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// A flat disk in the plane `y = 0`, centered on the origin.
+///
+/// The disk has no thickness, so it's hit at most once and it can't be used in CSG shapes.
+#[derive(Debug, Clone, Copy)]
+pub struct Disk {
+    pub radius: f64,
+}
+
+impl Disk {
+    /// Return the values `t` where the ray intersects the disk.
+    ///
+    /// Returns 0 or 1 values in a vector.
+    pub fn intersect_ray(&self, ray: Ray) -> Vec<f64> {
+        intersect_disk(ray, 0.0, self.radius).into_iter().collect()
+    }
+
+    /// Returns the surface normal, which is the same everywhere on the disk.
+    pub fn normal_at(&self, _p: Vec3) -> Vec3 {
+        Vec3::new(0.0, 1.0, 0.0)
+    }
+
+    /// Returns the texture coordinates at point `p`: the square around the disk is mapped to the
+    /// whole texture, with `v` along the z axis.
+    pub fn uv_at(&self, p: Vec3) -> (f64, f64) {
+        (0.5 + 0.5 * p.x / self.radius, 0.5 + 0.5 * p.z / self.radius)
+    }
+
+    /// Returns the direction in which `u` increases, which is the same everywhere on the disk.
+    pub fn tangent_at(&self, _p: Vec3) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+
+    /// Returns a box that contains the disk.
+    pub fn bounds(&self) -> Aabb {
+        let r = self.radius;
+        Aabb {
+            min: Vec3::new(-r, 0.0, -r),
+            max: Vec3::new(r, 0.0, r),
+        }
+    }
+}
+
+/// Returns the value `t` where the ray intersects a disk in the plane at height `y`, centered on
+/// the y axis. Also used for the caps of cylinders and cones.
+pub(crate) fn intersect_disk(ray: Ray, y: f64, radius: f64) -> Option<f64> {
+    if ray.direction.y == 0.0 {
+        return None;
+    }
+    let t = (y - ray.origin.y) / ray.direction.y;
+    let p = ray.at(t);
+    if p.x * p.x + p.z * p.z <= radius * radius {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+
+    #[test]
+    fn disk_intersect_ray() {
+        let disk = Disk { radius: 2.0 };
+        let ray = Ray {
+            origin: Vec3::new(1.0, 3.0, 1.0),
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        assert!(disk.intersect_ray(ray).approx_eq(&vec![3.0]));
+
+        // at an angle, from below
+        let ray = Ray {
+            origin: Vec3::new(0.0, -1.0, 0.0),
+            direction: Vec3::new(1.0, 0.5, 0.0),
+            time: 0.0,
+        };
+        assert!(disk.intersect_ray(ray).approx_eq(&vec![2.0]));
+
+        // hitting the plane outside the disk
+        let ray = Ray {
+            origin: Vec3::new(2.0, 3.0, 1.0),
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        assert!(disk.intersect_ray(ray).is_empty());
+
+        // parallel to the disk
+        let ray = Ray {
+            origin: Vec3::new(0.0, 0.0, -5.0),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        assert!(disk.intersect_ray(ray).is_empty());
+    }
+
+    #[test]
+    fn disk_uv_at() {
+        let disk = Disk { radius: 2.0 };
+        assert!(disk.uv_at(Vec3::new(0.0, 0.0, 0.0)).approx_eq((0.5, 0.5)));
+        assert!(disk.uv_at(Vec3::new(1.0, 0.0, -2.0)).approx_eq((0.75, 0.0)));
+    }
+}
@@ -0,0 +1,195 @@
+//! Defines a scene graph: a hierarchy of groups that each have a local transformation.
+
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::scene::{AmbientOcclusion, Instance, Light, LightSource, Motion, Object, Scene};
+use crate::transform::Transform;
+
+/// A scene described as a hierarchy of nodes.
+///
+/// Before rendering, the graph is flattened into a `Scene`, with each object's transformation
+/// combined with the transformations of all the groups it's in.
+#[derive(Debug)]
+pub struct SceneGraph {
+    pub background_color: Color,
+    pub ambient_occlusion: Option<AmbientOcclusion>,
+    pub root: Group,
+}
+
+/// A group of nodes that are placed in their parent's coordinate system by a transformation.
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub transform: Transform,
+    pub children: Vec<Node>,
+}
+
+/// A node in a scene graph.
+///
+/// The transformations of objects, instances and cameras, and the positions and directions of
+/// lights, are relative to the group that contains them.
+#[derive(Debug, Clone)]
+pub enum Node {
+    Group(Group),
+    Object(Object),
+    Instance(Instance),
+    Light(Light),
+    Camera(Camera),
+}
+
+impl SceneGraph {
+    /// Computes world transformations for all nodes and returns the resulting scene, together with
+    /// the cameras in the graph, in the order they're found in a depth-first traversal.
+    pub fn flatten(&self) -> (Scene, Vec<Camera>) {
+        let mut scene = Scene {
+            background_color: self.background_color,
+            lights: vec![],
+            objects: vec![],
+            instances: vec![],
+            ambient_occlusion: self.ambient_occlusion,
+        };
+        let mut cameras = vec![];
+        self.root
+            .flatten(Transform::IDENTITY, &mut scene, &mut cameras);
+        (scene, cameras)
+    }
+}
+
+impl Group {
+    fn flatten(&self, parent: Transform, scene: &mut Scene, cameras: &mut Vec<Camera>) {
+        let world = parent * self.transform;
+        for child in &self.children {
+            match child {
+                Node::Group(group) => group.flatten(world, scene, cameras),
+                Node::Object(object) => scene.objects.push(Object {
+                    transform: world * object.transform,
+                    motion: object.motion.map(|motion| transform_motion(motion, &world)),
+                    ..object.clone()
+                }),
+                Node::Instance(instance) => scene.instances.push(Instance {
+                    transform: world * instance.transform,
+                    ..instance.clone()
+                }),
+                Node::Light(light) => scene.lights.push(transform_light(light, &world)),
+                Node::Camera(camera) => cameras.push(Camera {
+                    transform: world * camera.transform,
+                    ..*camera
+                }),
+            }
+        }
+    }
+}
+
+fn transform_motion(motion: Motion, transform: &Transform) -> Motion {
+    match motion {
+        Motion::Linear { velocity } => Motion::Linear {
+            velocity: transform.vector(velocity),
+        },
+    }
+}
+
+fn transform_light(light: &Light, transform: &Transform) -> Light {
+    let source = match light.source {
+        LightSource::Ambient => LightSource::Ambient,
+        LightSource::Point { position } => LightSource::Point {
+            position: transform.point(position),
+        },
+        LightSource::Directional { direction } => LightSource::Directional {
+            direction: transform.vector(direction),
+        },
+    };
+    Light { source, ..*light }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+    use crate::scene::{Material, Shape, Sphere};
+    use crate::vec3::Vec3;
+    use std::f64::consts::FRAC_PI_2;
+
+    fn unit_sphere() -> Object {
+        Object {
+            shape: Shape::Sphere(Sphere {
+                center: Vec3::new(0.0, 0.0, 0.0),
+                radius: 1.0,
+            }),
+            material: Material::BLACK,
+            transform: Transform::IDENTITY,
+            motion: None,
+        }
+    }
+
+    #[test]
+    fn scene_graph_flatten() {
+        // an arm rotated around the y axis, with a sphere and a light at its end
+        let arm = Group {
+            transform: Transform::rotation(Vec3::new(0.0, 1.0, 0.0), FRAC_PI_2),
+            children: vec![
+                Node::Object(Object {
+                    transform: Transform::translation(Vec3::new(2.0, 0.0, 0.0)),
+                    motion: Some(Motion::Linear {
+                        velocity: Vec3::new(1.0, 0.0, 0.0),
+                    }),
+                    ..unit_sphere()
+                }),
+                Node::Light(Light {
+                    intensity: 0.5,
+                    source: LightSource::Point {
+                        position: Vec3::new(3.0, 0.0, 0.0),
+                    },
+                }),
+                Node::Light(Light {
+                    intensity: 0.5,
+                    source: LightSource::Directional {
+                        direction: Vec3::new(1.0, 0.0, 0.0),
+                    },
+                }),
+            ],
+        };
+        let graph = SceneGraph {
+            background_color: Color::WHITE,
+            ambient_occlusion: None,
+            root: Group {
+                transform: Transform::translation(Vec3::new(0.0, 1.0, 0.0)),
+                children: vec![
+                    Node::Group(arm),
+                    Node::Object(unit_sphere()),
+                    Node::Camera(Camera::DEFAULT),
+                ],
+            },
+        };
+        let (scene, cameras) = graph.flatten();
+
+        assert!(scene.background_color.approx_eq(Color::WHITE));
+        assert_eq!(scene.objects.len(), 2);
+        let origin = Vec3::new(0.0, 0.0, 0.0);
+        let p = scene.objects[0].transform.point(origin);
+        assert!(p.approx_eq(Vec3::new(0.0, 1.0, -2.0)));
+        match scene.objects[0].motion {
+            Some(Motion::Linear { velocity }) => {
+                assert!(velocity.approx_eq(Vec3::new(0.0, 0.0, -1.0)))
+            }
+            _ => panic!("expected linear motion"),
+        }
+        let p = scene.objects[1].transform.point(origin);
+        assert!(p.approx_eq(Vec3::new(0.0, 1.0, 0.0)));
+
+        assert_eq!(scene.lights.len(), 2);
+        match scene.lights[0].source {
+            LightSource::Point { position } => {
+                assert!(position.approx_eq(Vec3::new(0.0, 1.0, -3.0)))
+            }
+            _ => panic!("expected a point light"),
+        }
+        match scene.lights[1].source {
+            LightSource::Directional { direction } => {
+                assert!(direction.approx_eq(Vec3::new(0.0, 0.0, -1.0)))
+            }
+            _ => panic!("expected a directional light"),
+        }
+
+        assert_eq!(cameras.len(), 1);
+        assert!(cameras[0].position().approx_eq(Vec3::new(0.0, 1.0, 0.0)));
+    }
+}
@@ -0,0 +1,386 @@
+//! Defines a heightfield primitive for rendering terrain.
+
+use crate::aabb::Aabb;
+use crate::image::Image;
+use crate::noise::Perlin;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use std::io;
+
+/// A terrain surface defined by a grid of heights.
+///
+/// The grid covers the square from `x = 0, z = 0` to `x = 1, z = 1`, with `columns` samples along
+/// the x axis and `rows` samples along the z axis; the sample values are the y coordinates of the
+/// surface. Use the object's transformation to scale and place the terrain.
+///
+/// Each grid cell is made of two triangles, but those are never stored: rays walk through the grid
+/// cell by cell and only test the cells they cross.
+#[derive(Debug, Clone)]
+pub struct Heightfield {
+    columns: usize,
+    rows: usize,
+    heights: Vec<f64>,
+    min_height: f64,
+    max_height: f64,
+}
+
+impl Heightfield {
+    /// Returns a heightfield with the given heights, which are listed row by row, starting at
+    /// `z = 0`.
+    pub fn new(columns: usize, rows: usize, heights: Vec<f64>) -> Heightfield {
+        assert!(
+            columns >= 2 && rows >= 2,
+            "a heightfield needs at least 2x2 samples"
+        );
+        assert_eq!(heights.len(), columns * rows, "wrong number of heights");
+        let min_height = heights.iter().copied().fold(f64::INFINITY, f64::min);
+        let max_height = heights.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Heightfield {
+            columns,
+            rows,
+            heights,
+            min_height,
+            max_height,
+        }
+    }
+
+    /// Returns a heightfield with heights given by a function of `x` and `z`, which both go from 0
+    /// to 1.
+    pub fn from_fn(columns: usize, rows: usize, f: impl Fn(f64, f64) -> f64) -> Heightfield {
+        let heights = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let x = column as f64 / (columns - 1) as f64;
+                let z = row as f64 / (rows - 1) as f64;
+                f(x, z)
+            })
+            .collect();
+        Heightfield::new(columns, rows, heights)
+    }
+
+    /// Returns a heightfield with one sample per pixel of a grayscale image, where black is height
+    /// 0 and white is height 1.
+    ///
+    /// The image is used like a map seen from above, with its top edge at `z = 1`. For color
+    /// images, the average of the red, green, and blue values is used.
+    ///
+    /// Returns an error if the image is less than 2 pixels wide or high, since a heightfield needs
+    /// at least 2x2 samples.
+    pub fn from_image(image: &Image) -> io::Result<Heightfield> {
+        let (columns, rows) = (image.width(), image.height());
+        if columns < 2 || rows < 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "a heightfield needs an image of at least 2x2 pixels",
+            ));
+        }
+        let heights = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, rows - 1 - row)))
+            .map(|(x, y)| {
+                let c = image.pixel(x, y);
+                (c.0 + c.1 + c.2) / 3.0
+            })
+            .collect();
+        Ok(Heightfield::new(columns, rows, heights))
+    }
+
+    /// Returns a heightfield with heights between about 0 and 1 taken from fractal noise.
+    ///
+    /// `frequency` is the number of noise features across the grid and `octaves` is the number of
+    /// layers of finer detail (see `Perlin::fbm`).
+    pub fn from_noise(
+        columns: usize,
+        rows: usize,
+        noise: &Perlin,
+        frequency: f64,
+        octaves: usize,
+    ) -> Heightfield {
+        Heightfield::from_fn(columns, rows, |x, z| {
+            let p = Vec3::new(frequency * x, 0.0, frequency * z);
+            0.5 + 0.5 * noise.fbm(p, octaves)
+        })
+    }
+
+    /// Return the values `t` where the ray intersects the surface, in a sorted vector.
+    ///
+    /// This steps from cell to cell along the ray's path across the grid, with the DDA algorithm
+    /// by Amanatides and Woo, and skips cells where the ray is above or below all four heights.
+    pub fn intersect_ray(&self, ray: Ray) -> Vec<f64> {
+        let (t_start, t_end) = match self.bounds().intersect_ray(ray) {
+            Some(ts) => ts,
+            None => return vec![],
+        };
+        let max_column = self.columns as i64 - 2;
+        let max_row = self.rows as i64 - 2;
+
+        // walk through the grid in grid coordinates, where cells have size 1
+        let scale_x = (self.columns - 1) as f64;
+        let scale_z = (self.rows - 1) as f64;
+        let start = ray.at(t_start);
+        let mut column = ((start.x * scale_x).floor() as i64).clamp(0, max_column);
+        let mut row = ((start.z * scale_z).floor() as i64).clamp(0, max_row);
+        let (step_column, t_delta_x, mut t_next_x) =
+            dda_axis(ray.origin.x * scale_x, ray.direction.x * scale_x, column);
+        let (step_row, t_delta_z, mut t_next_z) =
+            dda_axis(ray.origin.z * scale_z, ray.direction.z * scale_z, row);
+
+        let mut ts = vec![];
+        let mut t = t_start;
+        loop {
+            let t_exit = t_next_x.min(t_next_z).min(t_end);
+            self.intersect_cell(ray, column as usize, row as usize, t, t_exit, &mut ts);
+            if t_exit >= t_end {
+                break;
+            }
+            if t_next_x < t_next_z {
+                column += step_column;
+                t = t_next_x;
+                t_next_x += t_delta_x;
+            } else {
+                row += step_row;
+                t = t_next_z;
+                t_next_z += t_delta_z;
+            }
+            if column < 0 || column > max_column || row < 0 || row > max_row {
+                break;
+            }
+        }
+
+        // a ray that crosses an edge hits the triangles on both sides
+        ts.sort_by(f64::total_cmp);
+        ts.dedup_by(|a, b| (*a - *b).abs() < 1E-9);
+        ts
+    }
+
+    /// Adds the values `t` where the ray hits the triangles in a cell. The ray is in the cell for
+    /// `t_enter <= t <= t_exit`.
+    fn intersect_cell(
+        &self,
+        ray: Ray,
+        column: usize,
+        row: usize,
+        t_enter: f64,
+        t_exit: f64,
+        ts: &mut Vec<f64>,
+    ) {
+        let corners = [
+            self.vertex(column, row),
+            self.vertex(column + 1, row),
+            self.vertex(column, row + 1),
+            self.vertex(column + 1, row + 1),
+        ];
+        let low = corners.iter().map(|p| p.y).fold(f64::INFINITY, f64::min);
+        let high = corners
+            .iter()
+            .map(|p| p.y)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let (y0, y1) = (ray.at(t_enter).y, ray.at(t_exit).y);
+        if y0.min(y1) > high || y0.max(y1) < low {
+            return;
+        }
+        let [p00, p10, p01, p11] = corners;
+        ts.extend(intersect_triangle(ray, p00, p10, p11));
+        ts.extend(intersect_triangle(ray, p00, p11, p01));
+    }
+
+    /// Returns the surface normal at point `p`, which must be on the surface.
+    ///
+    /// The normal is interpolated between the normals at the four corners of the cell, so the
+    /// terrain looks smooth rather than faceted.
+    pub fn normal_at(&self, p: Vec3) -> Vec3 {
+        let gx = (p.x * (self.columns - 1) as f64).clamp(0.0, (self.columns - 1) as f64);
+        let gz = (p.z * (self.rows - 1) as f64).clamp(0.0, (self.rows - 1) as f64);
+        let column = (gx.floor() as usize).min(self.columns - 2);
+        let row = (gz.floor() as usize).min(self.rows - 2);
+        let (u, v) = (gx - column as f64, gz - row as f64);
+        (1.0 - v)
+            * ((1.0 - u) * self.vertex_normal(column, row)
+                + u * self.vertex_normal(column + 1, row))
+            + v * ((1.0 - u) * self.vertex_normal(column, row + 1)
+                + u * self.vertex_normal(column + 1, row + 1))
+    }
+
+    /// Returns the texture coordinates at point `p`, which are its x and z coordinates, so the
+    /// texture is draped over the terrain like a map.
+    pub fn uv_at(&self, p: Vec3) -> (f64, f64) {
+        (p.x, p.z)
+    }
+
+    /// Returns the direction in which `u` increases, which is the x axis everywhere.
+    pub fn tangent_at(&self, _p: Vec3) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+
+    /// Returns a box that contains the surface.
+    pub fn bounds(&self) -> Aabb {
+        Aabb {
+            min: Vec3::new(0.0, self.min_height, 0.0),
+            max: Vec3::new(1.0, self.max_height, 1.0),
+        }
+    }
+
+    fn height(&self, column: usize, row: usize) -> f64 {
+        self.heights[row * self.columns + column]
+    }
+
+    /// Returns the point on the surface for a grid sample.
+    fn vertex(&self, column: usize, row: usize) -> Vec3 {
+        Vec3::new(
+            column as f64 / (self.columns - 1) as f64,
+            self.height(column, row),
+            row as f64 / (self.rows - 1) as f64,
+        )
+    }
+
+    /// Returns the (not normalized) normal at a grid sample, from the slope between its neighbors.
+    fn vertex_normal(&self, column: usize, row: usize) -> Vec3 {
+        let (left, right) = (column.saturating_sub(1), (column + 1).min(self.columns - 1));
+        let (back, front) = (row.saturating_sub(1), (row + 1).min(self.rows - 1));
+        let a = self.vertex(left, row);
+        let b = self.vertex(right, row);
+        let c = self.vertex(column, back);
+        let d = self.vertex(column, front);
+        let slope_x = (b.y - a.y) / (b.x - a.x);
+        let slope_z = (d.y - c.y) / (d.z - c.z);
+        Vec3::new(-slope_x, 1.0, -slope_z)
+    }
+}
+
+/// Returns the step direction, the `t` per cell, and the `t` of the first cell boundary, for one
+/// axis of the DDA algorithm. `origin` and `direction` are in grid coordinates.
+fn dda_axis(origin: f64, direction: f64, cell: i64) -> (i64, f64, f64) {
+    if direction > 0.0 {
+        (1, 1.0 / direction, (cell as f64 + 1.0 - origin) / direction)
+    } else if direction < 0.0 {
+        (-1, -1.0 / direction, (cell as f64 - origin) / direction)
+    } else {
+        (0, f64::INFINITY, f64::INFINITY)
+    }
+}
+
+/// Returns the value `t` where the ray intersects the triangle with corners `a`, `b`, and `c`,
+/// using the Möller–Trumbore algorithm.
+fn intersect_triangle(ray: Ray, a: Vec3, b: Vec3, c: Vec3) -> Option<f64> {
+    let ab = b - a;
+    let ac = c - a;
+    let p = ray.direction.cross(ac);
+    let det = ab.dot(p);
+    if det.abs() < 1E-12 {
+        return None;
+    }
+    let s = ray.origin - a;
+    let u = s.dot(p) / det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(ab);
+    let v = ray.direction.dot(q) / det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    Some(ac.dot(q) / det)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+    use crate::color::Color;
+
+    #[test]
+    fn heightfield_intersect_ray() {
+        // straight down onto a flat field
+        let flat = Heightfield::new(3, 3, vec![0.5; 9]);
+        let ray = Ray {
+            origin: Vec3::new(0.3, 2.0, 0.7),
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        assert!(flat.intersect_ray(ray).approx_eq(&vec![1.5]));
+
+        // horizontally into a ramp that goes up along x
+        let ramp = Heightfield::from_fn(5, 3, |x, _| x);
+        let ray = Ray {
+            origin: Vec3::new(-1.0, 0.25, 0.5),
+            direction: Vec3::new(2.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        assert!(ramp.intersect_ray(ray).approx_eq(&vec![0.625]));
+        let ray = Ray {
+            origin: Vec3::new(-1.0, 2.0, 0.5),
+            direction: Vec3::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        assert!(ramp.intersect_ray(ray).is_empty());
+
+        // through a ridge, crossing many cells diagonally
+        let ridge = Heightfield::from_fn(11, 11, |x, _| 0.5 - (x - 0.5) * (x - 0.5));
+        let ray = Ray {
+            origin: Vec3::new(-1.0, 0.45, -1.0),
+            direction: Vec3::new(1.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let ts = ridge.intersect_ray(ray);
+        assert!(ts.approx_eq(&vec![1.28, 1.72]), "got {:?}", ts);
+
+        // outside the grid
+        let ray = Ray {
+            origin: Vec3::new(1.5, 2.0, 0.5),
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        assert!(ridge.intersect_ray(ray).is_empty());
+    }
+
+    #[test]
+    fn heightfield_normal_at() {
+        let ramp = Heightfield::from_fn(5, 3, |x, _| x);
+        let n = ramp.normal_at(Vec3::new(0.3, 0.3, 0.6)).normalized();
+        assert!(n.approx_eq(Vec3::new(-1.0, 1.0, 0.0).normalized()));
+    }
+
+    #[test]
+    fn heightfield_from_image() -> io::Result<()> {
+        let image = Image::new(
+            2,
+            2,
+            vec![
+                Color::BLACK,
+                Color::WHITE,
+                Color(0.3, 0.6, 0.9),
+                Color::BLACK,
+            ],
+        );
+        let field = Heightfield::from_image(&image)?;
+        // the bottom row of the image is at z = 0
+        assert!(field.height(0, 0).approx_eq(0.6));
+        assert!(field.height(1, 0).approx_eq(0.0));
+        assert!(field.height(1, 1).approx_eq(1.0));
+        assert!(field.bounds().max.y.approx_eq(1.0));
+
+        // images that are a single pixel wide or high are rejected
+        let column = Image::new(1, 3, vec![Color::WHITE; 3]);
+        assert!(Heightfield::from_image(&column).is_err());
+        let row = Image::new(3, 1, vec![Color::WHITE; 3]);
+        assert!(Heightfield::from_image(&row).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn heightfield_from_noise() {
+        let field = Heightfield::from_noise(33, 33, &Perlin::new(3), 4.0, 4);
+        let bounds = field.bounds();
+        assert!(bounds.min.y < bounds.max.y);
+        assert!(bounds.min.y > -0.1 && bounds.max.y < 1.1);
+
+        // every vertical line through the grid hits the terrain once
+        for i in 0..10 {
+            let ray = Ray {
+                origin: Vec3::new(0.05 + 0.09 * i as f64, 5.0, 0.93 - 0.09 * i as f64),
+                direction: Vec3::new(0.0, -1.0, 0.0),
+                time: 0.0,
+            };
+            assert_eq!(field.intersect_ray(ray).len(), 1);
+        }
+    }
+}
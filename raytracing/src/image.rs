@@ -0,0 +1,234 @@
+//! Defines a type for images loaded from files.
+
+use crate::color::Color;
+use std::fs;
+use std::io;
+
+/// A rectangular image of RGB color values, e.g. loaded from a file.
+///
+/// Pixel `(0, 0)` is the top left corner.
+#[derive(Debug, Clone)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Image {
+    /// Returns an image with the given pixels, which are listed row by row, starting at the top.
+    ///
+    /// Panics if the image is empty, since textures can't look up pixels in an empty image, or if
+    /// the number of pixels doesn't match the size.
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Image {
+        assert!(width > 0 && height > 0, "image must not be empty");
+        assert_eq!(pixels.len(), width * height, "wrong number of pixels");
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Loads an image from a file in PPM (color) or PGM (grayscale) format, either binary or
+    /// plain text.
+    pub fn load_ppm(path: &str) -> io::Result<Image> {
+        Image::read_ppm(&fs::read(path)?)
+    }
+
+    /// Parses an image in PPM or PGM format (`P2`, `P3`, `P5` or `P6`).
+    pub fn read_ppm(data: &[u8]) -> io::Result<Image> {
+        let mut reader = PpmReader { data, pos: 0 };
+        let magic = reader.token()?;
+        let (channels, binary) = match magic.as_slice() {
+            b"P2" => (1, false),
+            b"P3" => (3, false),
+            b"P5" => (1, true),
+            b"P6" => (3, true),
+            _ => return Err(invalid_data("not a PPM or PGM file")),
+        };
+        let width = reader.number()?;
+        let height = reader.number()?;
+        let max_value = reader.number()?;
+        if max_value == 0 || max_value > 0xffff {
+            return Err(invalid_data("invalid maximum value"));
+        }
+        if width == 0 || height == 0 {
+            return Err(invalid_data("empty image"));
+        }
+        let count = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(channels))
+            .ok_or_else(|| invalid_data("image too large"))?;
+
+        // in binary files, a single whitespace character separates the header from the data
+        if binary {
+            reader.pos += 1;
+        }
+
+        // check the size against the data before allocating memory for the samples: binary samples
+        // take one or two bytes, and text samples at least two, with the whitespace between them
+        let remaining = data.len().saturating_sub(reader.pos);
+        let max_count = if !binary {
+            remaining / 2 + 1
+        } else if max_value < 0x100 {
+            remaining
+        } else {
+            remaining / 2
+        };
+        if count > max_count {
+            return Err(invalid_data("unexpected end of file"));
+        }
+        let mut samples = Vec::with_capacity(count);
+        for _ in 0..count {
+            let sample = if !binary {
+                reader.number()?
+            } else if max_value < 0x100 {
+                reader.byte()? as usize
+            } else {
+                (reader.byte()? as usize) << 8 | reader.byte()? as usize
+            };
+            if sample > max_value {
+                return Err(invalid_data("sample greater than maximum value"));
+            }
+            samples.push(sample as f64 / max_value as f64);
+        }
+
+        let pixels = samples
+            .chunks(channels)
+            .map(|c| match c {
+                [v] => Color(*v, *v, *v),
+                _ => Color(c[0], c[1], c[2]),
+            })
+            .collect();
+        Ok(Image::new(width, height, pixels))
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the color of the pixel in column `x` and row `y`.
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads the header fields and samples of a PPM file.
+struct PpmReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl PpmReader<'_> {
+    fn byte(&mut self) -> io::Result<u8> {
+        let b = *self
+            .data
+            .get(self.pos)
+            .ok_or_else(|| invalid_data("unexpected end of file"))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    /// Returns the next whitespace-separated token, skipping comments.
+    fn token(&mut self) -> io::Result<Vec<u8>> {
+        loop {
+            match self.data.get(self.pos) {
+                Some(b) if b.is_ascii_whitespace() => self.pos += 1,
+                Some(b'#') => {
+                    while !matches!(self.data.get(self.pos), None | Some(b'\n')) {
+                        self.pos += 1;
+                    }
+                }
+                Some(_) => break,
+                None => return Err(invalid_data("unexpected end of file")),
+            }
+        }
+        let start = self.pos;
+        while matches!(self.data.get(self.pos), Some(b) if !b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+        Ok(self.data[start..self.pos].to_vec())
+    }
+
+    fn number(&mut self) -> io::Result<usize> {
+        let token = self.token()?;
+        std::str::from_utf8(&token)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| invalid_data("invalid number"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+
+    #[test]
+    fn image_read_ppm() -> io::Result<()> {
+        // plain text, with a comment
+        let data = b"P3\n# a comment\n2 1\n255\n255 0 0  0 51 255\n";
+        let image = Image::read_ppm(data)?;
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert!(image.pixel(0, 0).approx_eq(Color(1.0, 0.0, 0.0)));
+        assert!(image.pixel(1, 0).approx_eq(Color(0.0, 0.2, 1.0)));
+
+        // binary
+        let mut data = b"P6 1 2 255\n".to_vec();
+        data.extend([0xff, 0xff, 0xff, 0, 0, 0x33]);
+        let image = Image::read_ppm(&data)?;
+        assert_eq!((image.width(), image.height()), (1, 2));
+        assert!(image.pixel(0, 0).approx_eq(Color::WHITE));
+        assert!(image.pixel(0, 1).approx_eq(Color(0.0, 0.0, 0.2)));
+        Ok(())
+    }
+
+    #[test]
+    fn image_read_pgm() -> io::Result<()> {
+        let image = Image::read_ppm(b"P2 2 2 10 0 5 10 2")?;
+        assert!(image.pixel(1, 0).approx_eq(Color(0.5, 0.5, 0.5)));
+        assert!(image.pixel(0, 1).approx_eq(Color::WHITE));
+
+        // binary with 16-bit samples
+        let mut data = b"P5 2 1 65535\n".to_vec();
+        data.extend([0xff, 0xff, 0x80, 0x00]);
+        let image = Image::read_ppm(&data)?;
+        assert!(image.pixel(0, 0).approx_eq(Color::WHITE));
+        assert!((image.pixel(1, 0).0 - 0.5).abs() < 1E-4);
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "image must not be empty")]
+    fn image_new_empty() {
+        Image::new(0, 3, vec![]);
+    }
+
+    #[test]
+    fn image_read_ppm_errors() {
+        assert!(Image::read_ppm(b"P7 1 1 255\n").is_err());
+        assert!(Image::read_ppm(b"P3 2 1 255\n0 0 0").is_err());
+        assert!(Image::read_ppm(b"P2 1 1 0\n0").is_err());
+        assert!(Image::read_ppm(b"P6 1 1 255\n\x01\x02").is_err());
+
+        // empty images, and samples above the maximum value
+        assert!(Image::read_ppm(b"P3 0 0 255\n").is_err());
+        assert!(Image::read_ppm(b"P2 0 1 255\n").is_err());
+        assert!(Image::read_ppm(b"P2 1 1 10\n11").is_err());
+        assert!(Image::read_ppm(b"P5 1 1 100\n\xff").is_err());
+
+        // sizes that overflow, or are larger than the data, fail without allocating memory
+        assert!(Image::read_ppm(b"P6 4294967296 4294967296 255\n").is_err());
+        assert!(Image::read_ppm(b"P6 200000 200000 255\n\x00\x00\x00").is_err());
+        assert!(Image::read_ppm(b"P3 200000 200000 255\n0 0 0").is_err());
+        assert!(Image::read_ppm(b"P5 2 1 65535\n\x00\x00\x00").is_err());
+    }
+}
@@ -1,15 +1,38 @@
+use camera::Camera;
 use color::Color;
 use raytracer::Raytracer;
-use scene::{Light, LightSource, Material, Scene, Sphere};
+use scene::{Light, LightSource, Material, Object, Scene, Shape, Specular, Sphere};
 use std::io;
+use transform::Transform;
 use vec3::Vec3;
 
+pub mod aabb;
+pub mod animation;
+pub mod aov;
 pub mod approx;
+pub mod camera;
 pub mod canvas;
 pub mod color;
+pub mod cone;
+pub mod csg;
+pub mod cuboid;
+pub mod cylinder;
+pub mod disk;
+pub mod graph;
+pub mod heightfield;
+pub mod image;
+pub mod matrix;
+pub mod noise;
+pub mod pattern;
+pub mod pbr;
+pub mod random;
 pub mod ray;
 pub mod raytracer;
 pub mod scene;
+pub mod sdf;
+pub mod texture;
+pub mod torus;
+pub mod transform;
 pub mod vec3;
 
 /// Run the raytracer.
@@ -41,44 +64,66 @@ fn raytracing() -> io::Result<()> {
                 },
             },
         ],
-        spheres: vec![
-            Sphere {
-                center: Vec3::new(0.0, -1.0, 3.0),
-                radius: 1.0,
+        objects: vec![
+            Object {
+                shape: Shape::Sphere(Sphere {
+                    center: Vec3::new(0.0, -1.0, 3.0),
+                    radius: 1.0,
+                }),
                 material: Material {
                     color: Color(1.0, 0.0, 0.0),
-                    specular: Some(500),
+                    specular: Some(Specular::Phong(500.0)),
                     reflective: 0.2,
+                    ..Material::BLACK
                 },
+                transform: Transform::IDENTITY,
+                motion: None,
             },
-            Sphere {
-                center: Vec3::new(2.0, 0.0, 4.0),
-                radius: 1.0,
+            Object {
+                shape: Shape::Sphere(Sphere {
+                    center: Vec3::new(2.0, 0.0, 4.0),
+                    radius: 1.0,
+                }),
                 material: Material {
                     color: Color(0.0, 0.0, 1.0),
-                    specular: Some(500),
+                    specular: Some(Specular::Phong(500.0)),
                     reflective: 0.3,
+                    ..Material::BLACK
                 },
+                transform: Transform::IDENTITY,
+                motion: None,
             },
-            Sphere {
-                center: Vec3::new(-2.0, 0.0, 4.0),
-                radius: 1.0,
+            Object {
+                shape: Shape::Sphere(Sphere {
+                    center: Vec3::new(-2.0, 0.0, 4.0),
+                    radius: 1.0,
+                }),
                 material: Material {
                     color: Color(0.0, 1.0, 0.0),
-                    specular: Some(10),
+                    specular: Some(Specular::Phong(10.0)),
                     reflective: 0.4,
+                    ..Material::BLACK
                 },
+                transform: Transform::IDENTITY,
+                motion: None,
             },
-            Sphere {
-                center: Vec3::new(0.0, -5001.0, 0.0),
-                radius: 5000.0,
+            Object {
+                shape: Shape::Sphere(Sphere {
+                    center: Vec3::new(0.0, -5001.0, 0.0),
+                    radius: 5000.0,
+                }),
                 material: Material {
                     color: Color(1.0, 1.0, 0.0),
-                    specular: Some(1000),
+                    specular: Some(Specular::Phong(1000.0)),
                     reflective: 0.5,
+                    ..Material::BLACK
                 },
+                transform: Transform::IDENTITY,
+                motion: None,
             },
         ],
+        instances: vec![],
+        ambient_occlusion: None,
     };
     let raytracer = Raytracer {
         canvas_width: 640,
@@ -86,6 +131,7 @@ fn raytracing() -> io::Result<()> {
         viewport_width: 1.0,
         viewport_height: 1.0,
         distance_to_projection_plane: 1.0,
+        camera: Camera::DEFAULT,
         scene,
     };
     let canvas = raytracer.go();
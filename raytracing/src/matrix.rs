@@ -0,0 +1,344 @@
+//! Defines 3×3 and 4×4 matrix types for linear and affine transformations.
+
+use crate::approx::ApproxEq;
+use crate::vec3::Vec3;
+use std::ops::Mul;
+
+/// A 3×3 matrix, stored in row-major order.
+#[derive(Debug, Clone, Copy)]
+pub struct Mat3(pub [[f64; 3]; 3]);
+
+impl Mat3 {
+    pub const IDENTITY: Mat3 = Mat3([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+
+    /// Returns a matrix that rotates by `angle` radians around `axis`, counter-clockwise when
+    /// looking against the direction of the axis.
+    pub fn rotation(axis: Vec3, angle: f64) -> Mat3 {
+        let Vec3 { x, y, z } = axis.normalized();
+        let (s, c) = angle.sin_cos();
+        let t = 1.0 - c;
+        Mat3([
+            [t * x * x + c, t * x * y - s * z, t * x * z + s * y],
+            [t * x * y + s * z, t * y * y + c, t * y * z - s * x],
+            [t * x * z - s * y, t * y * z + s * x, t * z * z + c],
+        ])
+    }
+
+    /// Returns a matrix that scales by the given factor along each axis.
+    pub fn scaling(factors: Vec3) -> Mat3 {
+        Mat3([
+            [factors.x, 0.0, 0.0],
+            [0.0, factors.y, 0.0],
+            [0.0, 0.0, factors.z],
+        ])
+    }
+
+    /// Returns the transposed matrix.
+    pub fn transpose(&self) -> Mat3 {
+        let m = self.0;
+        let mut r = [[0.0; 3]; 3];
+        for (i, row) in r.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = m[j][i];
+            }
+        }
+        Mat3(r)
+    }
+
+    /// Returns the determinant of the matrix.
+    pub fn determinant(&self) -> f64 {
+        let m = self.0;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// Returns the inverse matrix, or `None` if the matrix is singular.
+    pub fn inverse(&self) -> Option<Mat3> {
+        let det = self.determinant();
+        let m = self.0;
+        // the determinant is at most the product of the column sizes, so compare it to that, which
+        // doesn't treat matrices with small entries (like small scalings) as singular
+        let size = (0..3)
+            .map(|j| (0..3).map(|i| m[i][j].abs()).fold(0.0, f64::max))
+            .product::<f64>();
+        if det.abs() <= f64::EPSILON * size {
+            return None;
+        }
+        let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| {
+            m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+        };
+        Some(Mat3([
+            [
+                cofactor(1, 2, 1, 2) / det,
+                -cofactor(0, 2, 1, 2) / det,
+                cofactor(0, 1, 1, 2) / det,
+            ],
+            [
+                -cofactor(1, 2, 0, 2) / det,
+                cofactor(0, 2, 0, 2) / det,
+                -cofactor(0, 1, 0, 2) / det,
+            ],
+            [
+                cofactor(1, 2, 0, 1) / det,
+                -cofactor(0, 2, 0, 1) / det,
+                cofactor(0, 1, 0, 1) / det,
+            ],
+        ]))
+    }
+}
+
+impl ApproxEq for Mat3 {
+    fn approx_eq(self, other: Mat3) -> bool {
+        self.0
+            .iter()
+            .flatten()
+            .zip(other.0.iter().flatten())
+            .all(|(a, b)| a.approx_eq(*b))
+    }
+}
+
+impl Mul<Mat3> for Mat3 {
+    type Output = Mat3;
+
+    fn mul(self, other: Mat3) -> Mat3 {
+        let mut r = [[0.0; 3]; 3];
+        for (i, row) in r.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..3).map(|k| self.0[i][k] * other.0[k][j]).sum();
+            }
+        }
+        Mat3(r)
+    }
+}
+
+impl Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+
+    fn mul(self, v: Vec3) -> Vec3 {
+        let m = self.0;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+}
+
+/// A 4×4 matrix for affine transformations in homogeneous coordinates, stored in row-major order.
+#[derive(Debug, Clone, Copy)]
+pub struct Mat4(pub [[f64; 4]; 4]);
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4([
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
+
+    /// Returns an affine transformation matrix with the given linear part and translation.
+    pub fn affine(linear: Mat3, translation: Vec3) -> Mat4 {
+        let l = linear.0;
+        Mat4([
+            [l[0][0], l[0][1], l[0][2], translation.x],
+            [l[1][0], l[1][1], l[1][2], translation.y],
+            [l[2][0], l[2][1], l[2][2], translation.z],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Returns a matrix that moves points by `offset`.
+    pub fn translation(offset: Vec3) -> Mat4 {
+        Mat4::affine(Mat3::IDENTITY, offset)
+    }
+
+    /// Returns a matrix that rotates by `angle` radians around `axis` (see [`Mat3::rotation`]).
+    pub fn rotation(axis: Vec3, angle: f64) -> Mat4 {
+        Mat4::affine(Mat3::rotation(axis, angle), Vec3::new(0.0, 0.0, 0.0))
+    }
+
+    /// Returns a matrix that scales by the given factor along each axis.
+    pub fn scaling(factors: Vec3) -> Mat4 {
+        Mat4::affine(Mat3::scaling(factors), Vec3::new(0.0, 0.0, 0.0))
+    }
+
+    /// Returns the upper-left 3×3 matrix, i.e. the linear part of an affine transformation.
+    pub fn linear(&self) -> Mat3 {
+        let m = self.0;
+        Mat3([
+            [m[0][0], m[0][1], m[0][2]],
+            [m[1][0], m[1][1], m[1][2]],
+            [m[2][0], m[2][1], m[2][2]],
+        ])
+    }
+
+    /// Returns the transposed matrix.
+    pub fn transpose(&self) -> Mat4 {
+        let m = self.0;
+        let mut r = [[0.0; 4]; 4];
+        for (i, row) in r.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = m[j][i];
+            }
+        }
+        Mat4(r)
+    }
+
+    /// Returns the inverse matrix, or `None` if the matrix is singular.
+    ///
+    /// Uses Gauss-Jordan elimination with partial pivoting, so it works for any 4×4 matrix, not
+    /// just affine ones.
+    pub fn inverse(&self) -> Option<Mat4> {
+        let mut a = self.0;
+        let mut inv = Mat4::IDENTITY.0;
+        // pivots are compared to the size of their column, so small scalings aren't singular
+        let sizes = [0, 1, 2, 3].map(|j| (0..4).map(|i| a[i][j].abs()).fold(0.0, f64::max));
+        for col in 0..4 {
+            let pivot = (col..4)
+                .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+                .unwrap();
+            if a[pivot][col].abs() <= f64::EPSILON * sizes[col] {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let p = a[col][col];
+            for j in 0..4 {
+                a[col][j] /= p;
+                inv[col][j] /= p;
+            }
+            for row in 0..4 {
+                if row == col {
+                    continue;
+                }
+                let f = a[row][col];
+                for j in 0..4 {
+                    a[row][j] -= f * a[col][j];
+                    inv[row][j] -= f * inv[col][j];
+                }
+            }
+        }
+        Some(Mat4(inv))
+    }
+
+    /// Transforms a point, i.e. applies both the linear part and the translation.
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        let m = self.0;
+        let v = self.linear() * p;
+        let w = m[3][0] * p.x + m[3][1] * p.y + m[3][2] * p.z + m[3][3];
+        Vec3::new(v.x + m[0][3], v.y + m[1][3], v.z + m[2][3]) / w
+    }
+
+    /// Transforms a vector (a direction or offset), i.e. applies only the linear part.
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        self.linear() * v
+    }
+}
+
+impl ApproxEq for Mat4 {
+    fn approx_eq(self, other: Mat4) -> bool {
+        self.0
+            .iter()
+            .flatten()
+            .zip(other.0.iter().flatten())
+            .all(|(a, b)| a.approx_eq(*b))
+    }
+}
+
+impl Mul<Mat4> for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, other: Mat4) -> Mat4 {
+        let mut r = [[0.0; 4]; 4];
+        for (i, row) in r.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.0[i][k] * other.0[k][j]).sum();
+            }
+        }
+        Mat4(r)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    #[test]
+    fn mat3_rotation() {
+        let m = Mat3::rotation(Vec3::new(0.0, 0.0, 1.0), FRAC_PI_2);
+        assert!((m * Vec3::new(1.0, 0.0, 0.0)).approx_eq(Vec3::new(0.0, 1.0, 0.0)));
+        let m = Mat3::rotation(Vec3::new(0.0, 2.0, 0.0), FRAC_PI_2);
+        assert!((m * Vec3::new(0.0, 0.0, 1.0)).approx_eq(Vec3::new(1.0, 0.0, 0.0)));
+        let m = Mat3::rotation(Vec3::new(1.0, 1.0, 1.0), 2.0 * PI / 3.0);
+        assert!((m * Vec3::new(1.0, 0.0, 0.0)).approx_eq(Vec3::new(0.0, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn mat3_transpose_and_inverse() {
+        let m = Mat3([[2.0, 0.0, 1.0], [1.0, 3.0, 0.0], [0.0, 1.0, 4.0]]);
+        let t = Mat3([[2.0, 1.0, 0.0], [0.0, 3.0, 1.0], [1.0, 0.0, 4.0]]);
+        assert!(m.transpose().approx_eq(t));
+        assert!(m.determinant().approx_eq(25.0));
+        let inv = m.inverse().unwrap();
+        assert!((m * inv).approx_eq(Mat3::IDENTITY));
+        assert!((inv * m).approx_eq(Mat3::IDENTITY));
+        assert!(Mat3::scaling(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+        assert!(Mat3([[1.0, 2.0, 3.0], [2.0, 4.0, 6.0], [0.0, 1.0, 1.0]])
+            .inverse()
+            .is_none());
+
+        // a small uniform scaling has a tiny determinant, but isn't singular
+        let m = Mat3::scaling(Vec3::new(0.001, 0.001, 0.001));
+        assert!(m.determinant().approx_eq(0.0));
+        let inv = m.inverse().unwrap();
+        assert!(inv.approx_eq(Mat3::scaling(Vec3::new(1000.0, 1000.0, 1000.0))));
+    }
+
+    #[test]
+    fn mat4_mul() {
+        let a = Mat4::translation(Vec3::new(1.0, 2.0, 3.0));
+        let b = Mat4::scaling(Vec3::new(2.0, 2.0, 2.0));
+        let p = Vec3::new(1.0, 1.0, 1.0);
+        assert!((a * b)
+            .transform_point(p)
+            .approx_eq(Vec3::new(3.0, 4.0, 5.0)));
+        assert!((b * a)
+            .transform_point(p)
+            .approx_eq(Vec3::new(4.0, 6.0, 8.0)));
+        assert!((a * Mat4::IDENTITY).approx_eq(a));
+    }
+
+    #[test]
+    fn mat4_transform_point_and_vector() {
+        let m = Mat4::translation(Vec3::new(1.0, 2.0, 3.0))
+            * Mat4::rotation(Vec3::new(0.0, 0.0, 1.0), FRAC_PI_2);
+        let v = Vec3::new(1.0, 0.0, 0.0);
+        assert!(m.transform_point(v).approx_eq(Vec3::new(1.0, 3.0, 3.0)));
+        assert!(m.transform_vector(v).approx_eq(Vec3::new(0.0, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn mat4_transpose_and_inverse() {
+        let m = Mat4::translation(Vec3::new(1.0, -2.0, 3.0))
+            * Mat4::rotation(Vec3::new(1.0, 2.0, 0.5), 0.7)
+            * Mat4::scaling(Vec3::new(2.0, 0.5, 3.0));
+        assert!(m.transpose().transpose().approx_eq(m));
+        assert!(m.transpose().0[3][0].approx_eq(1.0));
+        let inv = m.inverse().unwrap();
+        assert!((m * inv).approx_eq(Mat4::IDENTITY));
+        assert!((inv * m).approx_eq(Mat4::IDENTITY));
+        assert!(Mat4::scaling(Vec3::new(1.0, 1.0, 0.0)).inverse().is_none());
+
+        // small scalings aren't singular, even next to a large translation
+        let m = Mat4::translation(Vec3::new(100.0, 0.0, 0.0))
+            * Mat4::scaling(Vec3::new(1E-10, 1E-10, 1E-10));
+        let inv = m.inverse().unwrap();
+        assert!((m * inv).approx_eq(Mat4::IDENTITY));
+        assert!(inv
+            .transform_point(Vec3::new(100.0, 0.0, 0.0))
+            .approx_eq(Vec3::new(0.0, 0.0, 0.0)));
+    }
+}
@@ -0,0 +1,173 @@
+//! Defines Perlin noise, for generating terrain and other natural-looking patterns.
+
+use crate::vec3::Vec3;
+
+/// Ken Perlin's "improved" gradient noise.
+///
+/// The noise is a smooth function that varies randomly at about the scale of 1 unit and is zero at
+/// all integer points. Different seeds give different, but repeatable, noise.
+#[derive(Debug, Clone)]
+pub struct Perlin {
+    permutation: Vec<usize>,
+}
+
+impl Perlin {
+    /// Returns noise with a permutation table shuffled using the given seed.
+    pub fn new(seed: u64) -> Perlin {
+        let mut table: Vec<usize> = (0..256).collect();
+        // a Fisher-Yates shuffle, with a linear congruential generator for random numbers
+        let mut state = seed;
+        for i in (1..table.len()).rev() {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let j = (state >> 33) as usize % (i + 1);
+            table.swap(i, j);
+        }
+        let permutation = table.iter().chain(table.iter()).copied().collect();
+        Perlin { permutation }
+    }
+
+    /// Returns the noise value at point `p`, which is between about -1 and 1.
+    pub fn noise(&self, p: Vec3) -> f64 {
+        let (xf, yf, zf) = (p.x.floor(), p.y.floor(), p.z.floor());
+        let (x, y, z) = (p.x - xf, p.y - yf, p.z - zf);
+        let xi = (xf as i64 & 255) as usize;
+        let yi = (yf as i64 & 255) as usize;
+        let zi = (zf as i64 & 255) as usize;
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let perm = &self.permutation;
+        let a = perm[xi] + yi;
+        let aa = perm[a] + zi;
+        let ab = perm[a + 1] + zi;
+        let b = perm[xi + 1] + yi;
+        let ba = perm[b] + zi;
+        let bb = perm[b + 1] + zi;
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, grad(perm[aa], x, y, z), grad(perm[ba], x - 1.0, y, z)),
+                lerp(
+                    u,
+                    grad(perm[ab], x, y - 1.0, z),
+                    grad(perm[bb], x - 1.0, y - 1.0, z),
+                ),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    grad(perm[aa + 1], x, y, z - 1.0),
+                    grad(perm[ba + 1], x - 1.0, y, z - 1.0),
+                ),
+                lerp(
+                    u,
+                    grad(perm[ab + 1], x, y - 1.0, z - 1.0),
+                    grad(perm[bb + 1], x - 1.0, y - 1.0, z - 1.0),
+                ),
+            ),
+        )
+    }
+
+    /// Returns fractal noise: the sum of `octaves` layers of noise, each with twice the frequency
+    /// and half the amplitude of the previous one. The result is scaled to be between about -1
+    /// and 1.
+    pub fn fbm(&self, p: Vec3, octaves: usize) -> f64 {
+        self.sum_octaves(p, octaves, |n| n)
+    }
+
+    /// Returns turbulence: like `fbm`, but adding up the absolute values of the noise, which gives
+    /// sharp creases where the noise crosses zero. The result is between 0 and about 1.
+    pub fn turbulence(&self, p: Vec3, octaves: usize) -> f64 {
+        self.sum_octaves(p, octaves, f64::abs)
+    }
+
+    fn sum_octaves(&self, p: Vec3, octaves: usize, f: impl Fn(f64) -> f64) -> f64 {
+        let mut sum = 0.0;
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut p = p;
+        for _ in 0..octaves {
+            sum += amplitude * f(self.noise(p));
+            total += amplitude;
+            amplitude *= 0.5;
+            p = 2.0 * p;
+        }
+        if total > 0.0 {
+            sum / total
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The curve 6t^5 - 15t^4 + 10t^3, used to blend between lattice points.
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+/// Returns the dot product of `(x, y, z)` with one of 12 gradient directions, picked by `hash`.
+fn grad(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+
+    #[test]
+    fn perlin_noise() {
+        let perlin = Perlin::new(1);
+
+        // zero at lattice points
+        assert!(perlin.noise(Vec3::new(3.0, -2.0, 7.0)).approx_eq(0.0));
+
+        // bounded, repeatable, and different between seeds
+        let other = Perlin::new(2);
+        let mut differs = false;
+        for i in 0..1000 {
+            let t = i as f64 * 0.137;
+            let p = Vec3::new(t, 0.5 * t + 0.3, -0.7 * t);
+            let n = perlin.noise(p);
+            assert!((-1.1..=1.1).contains(&n), "noise({:?}) == {}", p, n);
+            assert!(n.approx_eq(Perlin::new(1).noise(p)));
+            differs |= !n.approx_eq(other.noise(p));
+        }
+        assert!(differs);
+
+        // continuous
+        let p = Vec3::new(0.3, 0.4, 0.5);
+        let q = Vec3::new(0.3001, 0.4, 0.5);
+        assert!((perlin.noise(p) - perlin.noise(q)).abs() < 1E-3);
+    }
+
+    #[test]
+    fn perlin_fbm() {
+        let perlin = Perlin::new(7);
+        let p = Vec3::new(1.3, 2.7, -0.4);
+        assert!(perlin.fbm(p, 1).approx_eq(perlin.noise(p)));
+        let want = (perlin.noise(p) + 0.5 * perlin.noise(2.0 * p)) / 1.5;
+        assert!(perlin.fbm(p, 2).approx_eq(want));
+        assert!(perlin.fbm(p, 0).approx_eq(0.0));
+
+        let want = (perlin.noise(p).abs() + 0.5 * perlin.noise(2.0 * p).abs()) / 1.5;
+        assert!(perlin.turbulence(p, 2).approx_eq(want));
+    }
+}
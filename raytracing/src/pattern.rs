@@ -0,0 +1,218 @@
+//! Defines procedural patterns and color ramps, for textures computed from the point on a surface.
+
+use crate::color::Color;
+use crate::noise::Perlin;
+use crate::vec3::Vec3;
+use std::f64::consts::PI;
+
+/// A pattern defined in 3D space, with values between 0 and 1.
+///
+/// Most patterns repeat every unit; use the texture's scale to make them larger or smaller.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// A 3D checkerboard of unit cubes, alternating between 0 and 1.
+    Checker,
+
+    /// Stripes across the x axis, alternating between 0 and 1.
+    Stripes,
+
+    /// Concentric rings around the y axis, alternating between 0 and 1.
+    Rings,
+
+    /// A ramp from 0 to 1 along the x axis, repeating every unit.
+    Gradient,
+
+    /// Fractal noise (see `Perlin::fbm`), mapped to the range 0 to 1.
+    Noise { noise: Perlin, octaves: usize },
+
+    /// Turbulence (see `Perlin::turbulence`), e.g. for clouds or fire.
+    Turbulence { noise: Perlin, octaves: usize },
+
+    /// Soft stripes across the x axis, distorted by turbulence. `turbulence` is the amount of
+    /// distortion.
+    Marble {
+        noise: Perlin,
+        octaves: usize,
+        turbulence: f64,
+    },
+
+    /// Rings around the y axis, distorted by noise. `turbulence` is the amount of distortion.
+    Wood {
+        noise: Perlin,
+        octaves: usize,
+        turbulence: f64,
+    },
+}
+
+impl Pattern {
+    /// Returns the value of the pattern at point `p`.
+    pub fn value_at(&self, p: Vec3) -> f64 {
+        match self {
+            Pattern::Checker => parity(cell(p.x) + cell(p.y) + cell(p.z)),
+            Pattern::Stripes => parity(cell(p.x)),
+            Pattern::Rings => parity(cell((p.x * p.x + p.z * p.z).sqrt())),
+            Pattern::Gradient => p.x - p.x.floor(),
+            Pattern::Noise { noise, octaves } => 0.5 + 0.5 * noise.fbm(p, *octaves),
+            Pattern::Turbulence { noise, octaves } => noise.turbulence(p, *octaves),
+            Pattern::Marble {
+                noise,
+                octaves,
+                turbulence,
+            } => {
+                let x = p.x + turbulence * noise.turbulence(p, *octaves);
+                0.5 + 0.5 * (PI * x).sin()
+            }
+            Pattern::Wood {
+                noise,
+                octaves,
+                turbulence,
+            } => {
+                let r = (p.x * p.x + p.z * p.z).sqrt() + turbulence * noise.fbm(p, *octaves);
+                r - r.floor()
+            }
+        }
+    }
+}
+
+/// Returns the integer cell that `x` is in. The value is nudged a tiny bit, so that surfaces lying
+/// exactly on a cell boundary, like a disk at `y = 0`, don't flicker between two cells.
+fn cell(x: f64) -> i64 {
+    (x + 1E-9).floor() as i64
+}
+
+fn parity(n: i64) -> f64 {
+    n.rem_euclid(2) as f64
+}
+
+/// Maps values between 0 and 1 to colors, by interpolating between colors at given positions.
+#[derive(Debug, Clone)]
+pub struct ColorRamp {
+    stops: Vec<(f64, Color)>,
+}
+
+impl ColorRamp {
+    /// Returns a color ramp with the given positions and colors, which must be sorted by position.
+    /// Values before the first or after the last position get the first or last color.
+    pub fn new(stops: Vec<(f64, Color)>) -> ColorRamp {
+        assert!(!stops.is_empty(), "a color ramp needs at least one color");
+        assert!(
+            stops.windows(2).all(|w| w[0].0 <= w[1].0),
+            "color ramp positions must be sorted"
+        );
+        ColorRamp { stops }
+    }
+
+    /// Returns a color ramp that goes from color `a` at 0 to color `b` at 1.
+    pub fn two_colors(a: Color, b: Color) -> ColorRamp {
+        ColorRamp::new(vec![(0.0, a), (1.0, b)])
+    }
+
+    /// Returns the color for value `x`.
+    pub fn color_at(&self, x: f64) -> Color {
+        let i = self.stops.partition_point(|&(position, _)| position <= x);
+        if i == 0 {
+            return self.stops[0].1;
+        }
+        if i == self.stops.len() {
+            return self.stops[i - 1].1;
+        }
+        let (x0, c0) = self.stops[i - 1];
+        let (x1, c1) = self.stops[i];
+        let s = (x - x0) / (x1 - x0);
+        (1.0 - s) * c0 + s * c1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+
+    #[test]
+    fn pattern_value_at() {
+        let cases = [
+            (Pattern::Checker, Vec3::new(0.5, 0.5, 0.5), 0.0),
+            (Pattern::Checker, Vec3::new(1.5, 0.5, 0.5), 1.0),
+            (Pattern::Checker, Vec3::new(1.5, -0.5, 0.5), 0.0),
+            (Pattern::Checker, Vec3::new(0.5, 0.0, 0.5), 0.0),
+            (Pattern::Stripes, Vec3::new(0.5, 7.0, -3.0), 0.0),
+            (Pattern::Stripes, Vec3::new(-0.5, 7.0, -3.0), 1.0),
+            (Pattern::Rings, Vec3::new(0.0, 5.0, 0.5), 0.0),
+            (Pattern::Rings, Vec3::new(0.6, 5.0, 0.8), 1.0),
+            (Pattern::Gradient, Vec3::new(2.25, 0.0, 0.0), 0.25),
+            (Pattern::Gradient, Vec3::new(-0.25, 0.0, 0.0), 0.75),
+        ];
+        for (pattern, p, want) in cases {
+            let got = pattern.value_at(p);
+            assert!(got.approx_eq(want), "{:?} at {:?} == {}", pattern, p, got);
+        }
+    }
+
+    #[test]
+    fn noise_patterns() {
+        let noise = Perlin::new(1);
+        let patterns = [
+            Pattern::Noise {
+                noise: noise.clone(),
+                octaves: 4,
+            },
+            Pattern::Turbulence {
+                noise: noise.clone(),
+                octaves: 4,
+            },
+            Pattern::Marble {
+                noise: noise.clone(),
+                octaves: 4,
+                turbulence: 2.0,
+            },
+            Pattern::Wood {
+                noise,
+                octaves: 2,
+                turbulence: 0.3,
+            },
+        ];
+        for pattern in patterns {
+            let mut values = vec![];
+            for i in 0..200 {
+                let t = i as f64 * 0.173;
+                let p = Vec3::new(t, 0.3 * t, 1.0 - 0.5 * t);
+                values.push(pattern.value_at(p));
+            }
+            let (min, max) = values
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(a, b), &v| {
+                    (a.min(v), b.max(v))
+                });
+            assert!(
+                min >= -0.05 && max <= 1.05,
+                "{:?}: {} to {}",
+                pattern,
+                min,
+                max
+            );
+            assert!(max - min > 0.3, "{:?}: {} to {}", pattern, min, max);
+        }
+    }
+
+    #[test]
+    fn color_ramp() {
+        let red = Color(1.0, 0.0, 0.0);
+        let blue = Color(0.0, 0.0, 1.0);
+        let ramp = ColorRamp::two_colors(red, blue);
+        assert!(ramp.color_at(0.0).approx_eq(red));
+        assert!(ramp.color_at(0.25).approx_eq(Color(0.75, 0.0, 0.25)));
+        assert!(ramp.color_at(1.0).approx_eq(blue));
+
+        let ramp = ColorRamp::new(vec![
+            (0.2, red),
+            (0.5, Color::WHITE),
+            (0.6, Color::WHITE),
+            (0.8, blue),
+        ]);
+        assert!(ramp.color_at(-1.0).approx_eq(red));
+        assert!(ramp.color_at(0.35).approx_eq(Color(1.0, 0.5, 0.5)));
+        assert!(ramp.color_at(0.55).approx_eq(Color::WHITE));
+        assert!(ramp.color_at(0.7).approx_eq(Color(0.5, 0.5, 1.0)));
+        assert!(ramp.color_at(2.0).approx_eq(blue));
+    }
+}
@@ -0,0 +1,171 @@
+//! Defines a physically based material model, with a Cook-Torrance microfacet BRDF.
+
+use crate::color::Color;
+use crate::vec3::Vec3;
+use std::f64::consts::PI;
+
+/// Parameters for a physically based material, in the "metallic-roughness" form used by most
+/// modelling tools. The base color comes from the material's color or texture.
+///
+/// The surface is modelled as lots of tiny mirrors (microfacets), with the GGX distribution of
+/// orientations, the Smith shadowing term, and Schlick's approximation of the Fresnel term.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pbr {
+    /// How rough the surface is, from 0 (smooth, with sharp highlights) to 1 (rough, with broad
+    /// highlights).
+    pub roughness: f64,
+
+    /// How metallic the surface is, from 0 (a dielectric, like plastic) to 1 (a metal). Metals have
+    /// no diffuse reflection, and their highlights take on the base color.
+    pub metallic: f64,
+}
+
+/// The reflectance at normal incidence of most dielectrics, i.e. non-metals.
+const DIELECTRIC_F0: f64 = 0.04;
+
+impl Pbr {
+    /// Returns the reflectance at normal incidence, for a surface with base color `base`.
+    pub fn f0(&self, base: Color) -> Color {
+        (1.0 - self.metallic) * Color(DIELECTRIC_F0, DIELECTRIC_F0, DIELECTRIC_F0)
+            + self.metallic * base
+    }
+
+    /// Returns the fraction of the light coming from direction `l` that is reflected in direction
+    /// `v`, for a surface with normal `n` and base color `base`. None of the vectors need to be
+    /// normalized.
+    ///
+    /// The result includes the cosine factor `n·l`, and it's scaled so that a white, perfectly
+    /// diffuse surface gives just `n·l`, like the diffuse term of the classic lighting model.
+    pub fn reflectance(&self, base: Color, n: Vec3, v: Vec3, l: Vec3) -> Color {
+        let (n, v, l) = (n.normalized(), v.normalized(), l.normalized());
+        let n_dot_l = n.dot(l);
+        let n_dot_v = n.dot(v);
+        if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+            return Color::BLACK;
+        }
+        let h = (v + l).normalized();
+        let alpha = self.alpha();
+        let f = fresnel_schlick(self.f0(base), v.dot(h));
+        let d = ggx_distribution(n.dot(h), alpha);
+        let g = smith_g1(n_dot_l, alpha) * smith_g1(n_dot_v, alpha);
+        // the specular BRDF is D * G * F / (4 n·l n·v); multiplied by n·l and π for our scale
+        let specular = (PI * d * g / (4.0 * n_dot_v)) * f;
+        // energy that isn't reflected specularly is diffused, except by metals
+        let diffuse = (1.0 - self.metallic) * ((Color::WHITE - f) * base);
+        n_dot_l * diffuse + specular
+    }
+
+    /// Returns the fraction of ambient light that is diffusely reflected, for a surface with base
+    /// color `base`, seen at an angle with cosine `n_dot_v` to the normal.
+    pub fn ambient(&self, base: Color, n_dot_v: f64) -> Color {
+        let f = fresnel_schlick(self.f0(base), n_dot_v);
+        (1.0 - self.metallic) * ((Color::WHITE - f) * base)
+    }
+
+    /// Returns the GGX roughness parameter, which is the square of the perceptual roughness. It's
+    /// kept away from 0, where the highlights would become infinitely small and bright.
+    fn alpha(&self) -> f64 {
+        let r = self.roughness.clamp(0.0, 1.0);
+        (r * r).max(1E-3)
+    }
+}
+
+/// Returns Schlick's approximation of the Fresnel term: the reflectance of a surface with
+/// reflectance `f0` at normal incidence, for light at an angle with cosine `cos_theta`.
+pub fn fresnel_schlick(f0: Color, cos_theta: f64) -> Color {
+    let k = schlick_weight(cos_theta);
+    (1.0 - k) * f0 + k * Color::WHITE
+}
+
+/// Like `fresnel_schlick`, for a single reflectance value.
+pub fn schlick(f0: f64, cos_theta: f64) -> f64 {
+    let k = schlick_weight(cos_theta);
+    (1.0 - k) * f0 + k
+}
+
+fn schlick_weight(cos_theta: f64) -> f64 {
+    (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5)
+}
+
+/// The GGX (Trowbridge-Reitz) distribution of microfacet normals.
+fn ggx_distribution(n_dot_h: f64, alpha: f64) -> f64 {
+    let a2 = alpha * alpha;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+
+/// The Smith shadowing term for GGX, for one direction.
+fn smith_g1(n_dot_x: f64, alpha: f64) -> f64 {
+    let a2 = alpha * alpha;
+    2.0 * n_dot_x / (n_dot_x + (a2 + (1.0 - a2) * n_dot_x * n_dot_x).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+
+    #[test]
+    fn fresnel() {
+        let f0 = Color(0.04, 0.5, 1.0);
+        assert!(fresnel_schlick(f0, 1.0).approx_eq(f0));
+        assert!(fresnel_schlick(f0, 0.0).approx_eq(Color::WHITE));
+        let f = fresnel_schlick(f0, 0.5);
+        assert!(f.0 > 0.04 && f.0 < 0.1 && f.2.approx_eq(1.0));
+        assert!(schlick(0.5, 0.5).approx_eq(f.1));
+    }
+
+    #[test]
+    fn pbr_reflectance() {
+        let base = Color(0.8, 0.4, 0.2);
+        let n = Vec3::new(0.0, 1.0, 0.0);
+        let v = Vec3::new(0.0, 1.0, 1.0);
+        let l = Vec3::new(0.0, 1.0, -1.0);
+
+        // light from behind the surface, or seen from behind
+        let plastic = Pbr {
+            roughness: 0.5,
+            metallic: 0.0,
+        };
+        assert!(plastic
+            .reflectance(base, n, v, Vec3::new(0.0, -1.0, 1.0))
+            .approx_eq(Color::BLACK));
+        assert!(plastic
+            .reflectance(base, n, Vec3::new(0.0, -1.0, 1.0), l)
+            .approx_eq(Color::BLACK));
+
+        // metals have no diffuse reflection, so their reflection is tinted by the base color (with
+        // the light and camera in the same direction, where the Fresnel term is just the base color)
+        let metal = Pbr {
+            roughness: 0.5,
+            metallic: 1.0,
+        };
+        let Color(r, g, b) = metal.reflectance(base, n, v, v);
+        assert!((r / g).approx_eq(2.0) && (g / b).approx_eq(2.0));
+        assert!(metal.ambient(base, 1.0).approx_eq(Color::BLACK));
+
+        // away from the highlight, a rough dielectric is mostly diffuse
+        let rough = Pbr {
+            roughness: 1.0,
+            metallic: 0.0,
+        };
+        let l = Vec3::new(1.0, 1.0, 0.0);
+        let Color(r, _, b) = rough.reflectance(Color::WHITE, n, v, l);
+        assert!(r.approx_eq(b));
+        assert!(r > 0.6 && r < 0.8, "{}", r);
+
+        // smooth surfaces have smaller, brighter highlights
+        let smooth = Pbr {
+            roughness: 0.2,
+            metallic: 0.0,
+        };
+        let l = Vec3::new(0.0, 1.0, -1.0);
+        let peak = |pbr: Pbr| pbr.reflectance(Color::BLACK, n, v, l).0;
+        let off_peak = |pbr: Pbr| {
+            pbr.reflectance(Color::BLACK, n, v, Vec3::new(0.3, 1.0, -1.0))
+                .0
+        };
+        assert!(peak(smooth) > peak(rough));
+        assert!(off_peak(smooth) / peak(smooth) < off_peak(rough) / peak(rough));
+    }
+}
@@ -0,0 +1,104 @@
+//! Defines a small, fast random number generator, for effects that need random samples, like
+//! glossy reflections.
+
+use crate::vec3::Vec3;
+use std::cell::RefCell;
+
+/// A xorshift64* pseudo-random number generator. It's not suitable for cryptography, but it's fast
+/// and good enough for sampling.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Returns a generator that produces a repeatable sequence for the given seed.
+    pub fn new(seed: u64) -> Rng {
+        // the state must not be zero
+        let state = seed ^ 0x9E37_79B9_7F4A_7C15;
+        Rng {
+            state: if state == 0 { 1 } else { state },
+        }
+    }
+
+    /// Returns the next random number.
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a random number between 0 (inclusive) and 1 (exclusive).
+    pub fn next_f64(&mut self) -> f64 {
+        // the top 53 bits, which is the precision of an f64
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a random point `(x, y)` in the disk with radius 1 around the origin.
+    pub fn in_unit_disk(&mut self) -> (f64, f64) {
+        loop {
+            let x = 2.0 * self.next_f64() - 1.0;
+            let y = 2.0 * self.next_f64() - 1.0;
+            if x * x + y * y <= 1.0 {
+                return (x, y);
+            }
+        }
+    }
+
+    /// Returns a random point in the sphere with radius 1 around the origin.
+    pub fn in_unit_sphere(&mut self) -> Vec3 {
+        loop {
+            let p = Vec3::new(
+                2.0 * self.next_f64() - 1.0,
+                2.0 * self.next_f64() - 1.0,
+                2.0 * self.next_f64() - 1.0,
+            );
+            if p.dot(p) <= 1.0 {
+                return p;
+            }
+        }
+    }
+}
+
+thread_local! {
+    static RNG: RefCell<Rng> = RefCell::new(Rng::new(0));
+}
+
+/// Calls `f` with the current thread's random number generator.
+pub fn with_rng<T>(f: impl FnOnce(&mut Rng) -> T) -> T {
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rng_next_f64() {
+        let mut rng = Rng::new(42);
+        let values: Vec<f64> = (0..10000).map(|_| rng.next_f64()).collect();
+        assert!(values.iter().all(|x| (0.0..1.0).contains(x)));
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        assert!((mean - 0.5).abs() < 0.02, "mean == {}", mean);
+
+        // repeatable, and different between seeds
+        let mut again = Rng::new(42);
+        assert!(values.iter().all(|&x| x == again.next_f64()));
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+
+    #[test]
+    fn rng_in_unit_sphere() {
+        let mut rng = Rng::new(7);
+        let mut sum = Vec3::new(0.0, 0.0, 0.0);
+        for _ in 0..10000 {
+            let p = rng.in_unit_sphere();
+            assert!(p.len() <= 1.0);
+            sum = sum + p;
+        }
+        assert!((sum / 10000.0).len() < 0.05);
+    }
+}
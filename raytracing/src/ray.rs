@@ -5,6 +5,10 @@ use crate::vec3::Vec3;
 pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3, // must not be (0, 0, 0)
+
+    /// The time at which the ray is cast, for motion blur, in frames: a moving object is at its
+    /// starting position at time 0, and has moved by its velocity at time 1.
+    pub time: f64,
 }
 
 impl Ray {
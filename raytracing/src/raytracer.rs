@@ -1,8 +1,14 @@
+use crate::animation::Animation;
+use crate::aov::Aovs;
+use crate::camera::Camera;
 use crate::canvas::Canvas;
 use crate::color::Color;
+use crate::pbr::{fresnel_schlick, schlick, Pbr};
+use crate::random;
 use crate::ray::Ray;
-use crate::scene::{LightSource, Scene, Sphere};
+use crate::scene::{AmbientOcclusion, Hit, Light, LightSource, Scene, Specular};
 use crate::vec3::Vec3;
+use std::io;
 use std::ops::Range;
 
 /// Renders a static image with raytracing.
@@ -12,40 +18,157 @@ pub struct Raytracer {
     pub viewport_width: f64,
     pub viewport_height: f64,
     pub distance_to_projection_plane: f64,
+    pub camera: Camera,
     pub scene: Scene,
 }
 
 impl Raytracer {
     /// Run the raytracer.
     pub fn go(&self) -> Canvas {
+        self.render(&self.camera).0
+    }
+
+    /// Run the raytracer, returning the image and its auxiliary outputs, like depth and normals.
+    pub fn go_with_aovs(&self) -> (Canvas, Aovs) {
+        self.render(&self.camera)
+    }
+
+    /// Run the raytracer for a stereo image, returning the images for the left and right eye (see
+    /// `Camera::stereo_pair`). Use `Canvas::side_by_side` or `Canvas::anaglyph` to combine them.
+    pub fn go_stereo(
+        &self,
+        interocular_distance: f64,
+        convergence_distance: f64,
+    ) -> (Canvas, Canvas) {
+        let (left, right) = self
+            .camera
+            .stereo_pair(interocular_distance, convergence_distance);
+        (self.render(&left).0, self.render(&right).0)
+    }
+
+    /// Run the raytracer for each frame of an animation, and save the images to PNG files called
+    /// `frame_0001.png`, `frame_0002.png` etc. in `directory`.
+    pub fn go_animation(&self, animation: &Animation, directory: &str) -> io::Result<()> {
+        for frame in 1..=animation.frames {
+            let (camera, scene) = animation.frame(&self.camera, &self.scene, frame as f64);
+            let raytracer = Raytracer {
+                camera,
+                scene,
+                ..*self
+            };
+            let path = format!("{}/frame_{:04}.png", directory, frame);
+            raytracer.go().save_to_png(&path)?;
+        }
+        Ok(())
+    }
+
+    /// Renders the scene as seen from `camera`, returning the image and its auxiliary outputs.
+    fn render(&self, camera: &Camera) -> (Canvas, Aovs) {
         let mut canvas = Canvas::new(self.canvas_width, self.canvas_height);
-        let origin = Vec3::new(0.0, 0.0, 0.0);
+        let mut aovs = Aovs::new(self.canvas_width, self.canvas_height);
         let cw = self.canvas_width as i32;
         let ch = self.canvas_height as i32;
         let recursion_depth = 3;
-        let offset = vec![-0.4, -0.2, 0.0, 0.2, 0.4];
         for x in (-cw / 2)..(cw / 2) {
             for y in (-ch / 2)..(ch / 2) {
-                let mut average_color = Color::BLACK;
-                for x_offset in offset.iter() {
-                    for y_offset in offset.iter() {
-                        let direction =
-                            self.canvas_to_viewport(x as f64 + x_offset, y as f64 + y_offset);
-                        let color = trace_ray(
-                            &self.scene,
-                            Ray { origin, direction },
-                            1.0..f64::INFINITY,
-                            recursion_depth,
-                        );
-                        average_color += 0.04 * color;
+                let mut direct = Color::BLACK;
+                let mut reflected = Color::BLACK;
+                let mut albedo = Color::BLACK;
+                // distance of the sample that the depth, normal and object index come from
+                let mut nearest = f64::INFINITY;
+                for (ray, distance) in self.pixel_rays(camera, x, y) {
+                    let closest =
+                        closest_indexed_intersection(&self.scene, ray, 1.0..f64::INFINITY);
+                    let is_nearest = distance < nearest;
+                    if is_nearest {
+                        nearest = distance;
+                    }
+                    let (index, hit) = match closest {
+                        Some(closest) => closest,
+                        None => {
+                            direct += SAMPLE_WEIGHT * self.scene.background_color;
+                            if is_nearest {
+                                aovs.depth.put_pixel(x, y, f64::INFINITY);
+                                aovs.normal.put_pixel(x, y, Vec3::new(0.0, 0.0, 0.0));
+                                aovs.object.put_pixel(x, y, None);
+                            }
+                            continue;
+                        }
+                    };
+                    let shading = shade(&self.scene, ray, &hit, true, recursion_depth);
+                    direct += SAMPLE_WEIGHT * shading.direct;
+                    reflected += SAMPLE_WEIGHT * shading.reflected;
+                    albedo += SAMPLE_WEIGHT * hit.material.color_at(&hit);
+                    if is_nearest {
+                        aovs.depth.put_pixel(x, y, hit.t);
+                        let n = shading_normal(ray, &hit).normalized();
+                        aovs.normal.put_pixel(x, y, n);
+                        aovs.object.put_pixel(x, y, Some(index));
                     }
                 }
-                canvas.put_pixel(x, y, average_color);
+                canvas.put_pixel(x, y, direct + reflected);
+                aovs.direct.put_pixel(x, y, direct);
+                aovs.reflected.put_pixel(x, y, reflected);
+                aovs.albedo.put_pixel(x, y, albedo);
+            }
+        }
+        (canvas, aovs)
+    }
+
+    /// Renders only the ambient occlusion as a grayscale image, from black where ambient light is
+    /// blocked completely to white where it isn't blocked at all, e.g. where rays hit nothing.
+    /// The scene's own ambient occlusion settings are ignored.
+    pub fn go_ambient_occlusion(&self, settings: AmbientOcclusion) -> Canvas {
+        let mut canvas = Canvas::new(self.canvas_width, self.canvas_height);
+        let cw = self.canvas_width as i32;
+        let ch = self.canvas_height as i32;
+        for x in (-cw / 2)..(cw / 2) {
+            for y in (-ch / 2)..(ch / 2) {
+                let mut visibility = 0.0;
+                for (ray, _) in self.pixel_rays(&self.camera, x, y) {
+                    visibility += SAMPLE_WEIGHT
+                        * match closest_intersection(&self.scene, ray, 1.0..f64::INFINITY) {
+                            Some(hit) => {
+                                let n = shading_normal(ray, &hit);
+                                ambient_visibility(
+                                    &self.scene,
+                                    Some(occlusion_per_sample(settings)),
+                                    ray.at(hit.t),
+                                    ray.time,
+                                    n,
+                                )
+                            }
+                            None => 1.0,
+                        };
+                }
+                canvas.put_pixel(x, y, Color(visibility, visibility, visibility));
             }
         }
         canvas
     }
 
+    /// Returns the camera rays for the samples of pixel `(x, y)`, which are averaged for
+    /// anti-aliasing, and the distance of each sample from the center of the pixel. Samples
+    /// outside the image of the projection, e.g. outside a fisheye image circle, are skipped,
+    /// i.e. black.
+    fn pixel_rays<'a>(
+        &'a self,
+        camera: &'a Camera,
+        x: i32,
+        y: i32,
+    ) -> impl Iterator<Item = (Ray, f64)> + 'a {
+        let offsets = SAMPLE_OFFSETS
+            .iter()
+            .flat_map(|&dx| SAMPLE_OFFSETS.iter().map(move |&dy| (dx, dy)));
+        offsets.filter_map(move |(dx, dy)| {
+            let p = self.canvas_to_viewport(x as f64 + dx, y as f64 + dy);
+            let ray = camera
+                .projection
+                .ray(p, self.viewport_width, self.viewport_height)?;
+            Some((camera.ray(ray), dx.hypot(dy)))
+        })
+    }
+
     fn canvas_to_viewport(&self, x: f64, y: f64) -> Vec3 {
         Vec3 {
             x: x * self.viewport_width / (self.canvas_width as f64),
@@ -55,86 +178,317 @@ impl Raytracer {
     }
 }
 
+/// The offsets of the samples for anti-aliasing from the center of a pixel, in both directions.
+const SAMPLE_OFFSETS: [f64; 5] = [-0.4, -0.2, 0.0, 0.2, 0.4];
+
+/// The weight of each sample in the color of a pixel.
+const SAMPLE_WEIGHT: f64 = 1.0 / (SAMPLE_OFFSETS.len() * SAMPLE_OFFSETS.len()) as f64;
+
+/// Returns the ambient occlusion settings for each of the samples of a pixel, which share the rays
+/// for the pixel between them, with at least one ray each.
+fn occlusion_per_sample(settings: AmbientOcclusion) -> AmbientOcclusion {
+    let count = SAMPLE_OFFSETS.len() * SAMPLE_OFFSETS.len();
+    AmbientOcclusion {
+        samples: settings.samples.div_ceil(count),
+        ..settings
+    }
+}
+
 /// Finds the first intersection between the ray and an object in the scene.
 ///
 /// More precisely, for a ray defined by `origin + t*direction`, it looks for intersections with
 /// objects in the scene for which `t` is in the given range, and if it finds any, selects the one
-/// with the smallest `t` and returns the object and `t`.
-fn closest_intersection(scene: &Scene, ray: Ray, t_range: Range<f64>) -> Option<(Sphere, f64)> {
-    let mut closest: Option<(Sphere, f64)> = None;
-    for sphere in &scene.spheres {
-        let ts = sphere.intersect_ray(ray);
-        for t in ts {
-            if !t_range.contains(&t) {
-                continue;
-            }
-            if closest.map_or(true, |(_, closest_t)| t < closest_t) {
-                closest = Some((*sphere, t));
-            }
-        }
-    }
-    closest
+/// with the smallest `t` and returns it.
+fn closest_intersection(scene: &Scene, ray: Ray, t_range: Range<f64>) -> Option<Hit<'_>> {
+    closest_indexed_intersection(scene, ray, t_range).map(|(_, hit)| hit)
+}
+
+/// Like `closest_intersection`, but also returns the index of the object that's hit, counting the
+/// scene's objects first and then its instances.
+fn closest_indexed_intersection(
+    scene: &Scene,
+    ray: Ray,
+    t_range: Range<f64>,
+) -> Option<(usize, Hit<'_>)> {
+    let object_hits = scene
+        .objects
+        .iter()
+        .map(|object| object.closest_hit(ray, &t_range));
+    let instance_hits = scene
+        .instances
+        .iter()
+        .map(|instance| instance.closest_hit(ray, &t_range));
+    object_hits
+        .chain(instance_hits)
+        .enumerate()
+        .filter_map(|(i, hit)| Some((i, hit?)))
+        .min_by(|(_, a), (_, b)| a.t.total_cmp(&b.t))
+}
+
+#[cfg(test)]
+thread_local! {
+    /// The number of rays traced with `trace_ray` on this thread, for tests.
+    static TRACED_RAYS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
 }
 
 /// Runs the raytracing algorithm for one pixel in the image.
 fn trace_ray(scene: &Scene, ray: Ray, t_range: Range<f64>, recursion_depth: i32) -> Color {
+    #[cfg(test)]
+    TRACED_RAYS.with(|n| n.set(n.get() + 1));
     let closest = closest_intersection(scene, ray, t_range);
-    closest.map_or(scene.background_color, |(sphere, t)| {
-        // compute local color
-        let p = ray.at(t); // point where the ray intersects the sphere
-        let n = (p - sphere.center).normalized(); // normal
-        let material = sphere.material;
-        let local_color =
-            material.color * compute_lighting(scene, p, n, -ray.direction, material.specular);
-
-        // check if we need the reflective color
-        let r = material.reflective;
-        if recursion_depth <= 0 || r <= 0.0 {
-            return local_color;
-        }
+    closest.map_or(scene.background_color, |hit| {
+        let shading = shade(scene, ray, &hit, false, recursion_depth);
+        shading.direct + shading.reflected
+    })
+}
 
-        // compute reflected color
-        let reflected_color = trace_ray(
+/// The color of a point on a surface, split into the light from the light sources that's reflected
+/// directly, and the light reflected from other objects.
+struct Shading {
+    direct: Color,
+    reflected: Color,
+}
+
+/// Returns the normal at the point where the ray hits a surface, facing the ray, since open shapes
+/// like disks can be seen from both sides.
+fn shading_normal(ray: Ray, hit: &Hit) -> Vec3 {
+    let n = hit.material.normal_at(hit);
+    if hit.normal.dot(ray.direction) > 0.0 {
+        -n
+    } else {
+        n
+    }
+}
+
+/// Computes the color of the surface where the ray hits it.
+///
+/// Ambient occlusion and rough reflections with several rays are only computed if `camera_ray` is
+/// set, i.e. for surfaces seen directly by the camera, since they're too costly to repeat for every
+/// reflection, and hardly visible in reflections.
+fn shade(scene: &Scene, ray: Ray, hit: &Hit, camera_ray: bool, recursion_depth: i32) -> Shading {
+    // compute local color
+    let p = ray.at(hit.t); // point where the ray intersects the object
+    let material = hit.material;
+    let n = shading_normal(ray, hit);
+    let v = -ray.direction;
+    let has_ambient = scene
+        .lights
+        .iter()
+        .any(|light| matches!(light.source, LightSource::Ambient));
+    let ambient = if has_ambient {
+        let occlusion = scene
+            .ambient_occlusion
+            .filter(|_| camera_ray)
+            .map(occlusion_per_sample);
+        ambient_visibility(scene, occlusion, p, ray.time, n)
+    } else {
+        1.0
+    };
+    // rough reflections of reflections use a single ray, so the number of rays doesn't grow
+    // exponentially with the recursion depth
+    let glossy_samples = if camera_ray {
+        material.glossy_samples
+    } else {
+        1
+    };
+    let reflection = Ray {
+        origin: p,
+        direction: reflect_ray(ray.direction, n),
+        time: ray.time,
+    };
+    if let Some(pbr) = &material.pbr {
+        let (direct, reflectance) = pbr_lighting(scene, ray, hit, n, pbr, ambient);
+        if recursion_depth <= 0 {
+            return Shading {
+                direct,
+                reflected: Color::BLACK,
+            };
+        }
+        let reflected_color = trace_reflection(
             scene,
-            Ray {
-                origin: p,
-                direction: reflect_ray(ray.direction, n),
-            },
-            0.001..f64::INFINITY,
-            recursion_depth - 1,
+            reflection,
+            n,
+            pbr.roughness,
+            glossy_samples,
+            recursion_depth,
         );
-        local_color * (1.0 - r) + reflected_color * r
-    })
+        return Shading {
+            direct,
+            reflected: reflectance * reflected_color,
+        };
+    }
+    let local_color = material.color_at(hit)
+        * compute_lighting(scene, p, ray.time, n, v, material.specular, ambient);
+
+    // check if we need the reflective color
+    let r = material.reflective;
+    if recursion_depth <= 0 || r <= 0.0 {
+        return Shading {
+            direct: local_color,
+            reflected: Color::BLACK,
+        };
+    }
+    let r = if material.fresnel {
+        schlick(r, n.dot(v) / v.len())
+    } else {
+        r
+    };
+
+    // compute reflected color
+    let reflected_color = trace_reflection(
+        scene,
+        reflection,
+        n,
+        material.roughness,
+        glossy_samples,
+        recursion_depth,
+    );
+    Shading {
+        direct: local_color * (1.0 - r),
+        reflected: r * (material.reflection_tint * reflected_color),
+    }
+}
+
+/// Computes the light from the light sources reflected directly at a point on a surface with a
+/// physically based material, and the fraction of the scene's reflection in the surface that
+/// reaches the viewer, given by the Fresnel term.
+///
+/// Argument `n` is the surface normal where `ray` hits the surface, and `ambient` the fraction of
+/// ambient light that reaches it.
+fn pbr_lighting(
+    scene: &Scene,
+    ray: Ray,
+    hit: &Hit,
+    n: Vec3,
+    pbr: &Pbr,
+    ambient: f64,
+) -> (Color, Color) {
+    let (v, time) = (-ray.direction, ray.time);
+    let p = ray.at(hit.t);
+    let base = hit.material.color_at(hit);
+    let mut color = Color::BLACK;
+    let n_dot_v = n.dot(v) / (n.len() * v.len());
+    for light in &scene.lights {
+        if let LightSource::Ambient = light.source {
+            color += light.intensity * ambient * pbr.ambient(base, n_dot_v);
+        } else if let Some((intensity, l)) = unshadowed_light(scene, p, time, light) {
+            color += intensity * pbr.reflectance(base, n, v, l);
+        }
+    }
+    (color, fresnel_schlick(pbr.f0(base), n_dot_v))
+}
+
+/// Computes the color reflected by a surface with normal `n`, for a mirror reflection along
+/// `reflection`.
+///
+/// For rough surfaces, i.e. `roughness` above 0, the reflection is blurred by averaging `samples`
+/// rays, jittered randomly around the mirror direction by up to `roughness`.
+fn trace_reflection(
+    scene: &Scene,
+    reflection: Ray,
+    n: Vec3,
+    roughness: f64,
+    samples: usize,
+    recursion_depth: i32,
+) -> Color {
+    let trace = |direction| {
+        let ray = Ray {
+            direction,
+            ..reflection
+        };
+        trace_ray(scene, ray, 0.001..f64::INFINITY, recursion_depth - 1)
+    };
+    if roughness <= 0.0 {
+        return trace(reflection.direction);
+    }
+    let direction = reflection.direction.normalized();
+    let samples = samples.max(1);
+    let mut color = Color::BLACK;
+    for _ in 0..samples {
+        let d = direction + roughness * random::with_rng(|rng| rng.in_unit_sphere());
+        // jittered rays that would go into the surface are reflected back out
+        let d = if d.dot(n) < 0.0 { reflect_ray(d, n) } else { d };
+        color += trace(d);
+    }
+    (1.0 / samples as f64) * color
+}
+
+/// Returns the intensity of a point or directional light at point `p`, and the direction from `p`
+/// to the light, or `None` if the light is blocked by an object at time `time`.
+fn unshadowed_light(scene: &Scene, p: Vec3, time: f64, light: &Light) -> Option<(f64, Vec3)> {
+    let (l, t_max) = match light.source {
+        LightSource::Point { position } => (position - p, 1.0),
+        LightSource::Directional { direction } => (direction, f64::INFINITY),
+        LightSource::Ambient => return None,
+    };
+
+    // shadow check
+    let ray = Ray {
+        origin: p,
+        direction: l,
+        time,
+    };
+    if closest_intersection(scene, ray, 0.001..t_max).is_some() {
+        return None;
+    }
+    Some((light.intensity, l))
+}
+
+/// Returns the fraction of ambient light that reaches point `p` on a surface with normal `n` at time
+/// `time`, i.e. isn't blocked by nearby objects (see `AmbientOcclusion`). Without ambient occlusion,
+/// it's always 1.
+fn ambient_visibility(
+    scene: &Scene,
+    settings: Option<AmbientOcclusion>,
+    p: Vec3,
+    time: f64,
+    n: Vec3,
+) -> f64 {
+    let settings = match settings {
+        Some(settings) if settings.samples > 0 => settings,
+        _ => return 1.0,
+    };
+    let n = n.normalized();
+    let mut unblocked = 0;
+    for _ in 0..settings.samples {
+        // the sum of the normal and a random point in the unit sphere is in the hemisphere around
+        // the normal, with more rays close to the normal, where blocking objects matter more
+        let direction = n + random::with_rng(|rng| rng.in_unit_sphere());
+        let ray = Ray {
+            origin: p,
+            direction,
+            time,
+        };
+        let t_max = settings.distance / direction.len();
+        if closest_intersection(scene, ray, 0.001..t_max).is_none() {
+            unblocked += 1;
+        }
+    }
+    unblocked as f64 / settings.samples as f64
 }
 
 /// Compute the light intensity for a point on a surface in the scene, taking into account shadows
 /// but not reflections.
 ///
-/// Arguments `p` and `n` are the point and its surface normal. Taking into account specular
+/// Arguments `p` and `n` are the point and its surface normal, and `time` is the time of the ray,
+/// for moving objects that cast shadows. Taking into account specular
 /// reflection (for shiny objects) requires two more arguments: `v` for the direction to the
-/// camera, and the `specular` parameters of the object's material.
-fn compute_lighting(scene: &Scene, p: Vec3, n: Vec3, v: Vec3, specular: Option<i32>) -> f64 {
+/// camera, and the `specular` parameters of the object's material. Ambient light is scaled by
+/// `ambient`, the fraction of it that reaches `p` (see `ambient_visibility`).
+fn compute_lighting(
+    scene: &Scene,
+    p: Vec3,
+    time: f64,
+    n: Vec3,
+    v: Vec3,
+    specular: Option<Specular>,
+    ambient: f64,
+) -> f64 {
     let mut i = 0.0;
     for light in &scene.lights {
-        let intensity = light.intensity;
         if let LightSource::Ambient = light.source {
-            i += intensity;
-        } else {
-            let (intensity, l, t_max) = match light.source {
-                LightSource::Point { position } => (intensity, position - p, 1.0),
-                LightSource::Directional { direction } => (intensity, direction, f64::INFINITY),
-                _ => unreachable!(),
-            };
-
-            // shadow check
-            let ray = Ray {
-                origin: p,
-                direction: l,
-            };
-            if closest_intersection(scene, ray, 0.001..t_max).is_some() {
-                continue;
-            }
-
+            i += light.intensity * ambient;
+        } else if let Some((intensity, l)) = unshadowed_light(scene, p, time, light) {
             // diffuse
             let n_dot_l = n.dot(l);
             let diffuse = if n_dot_l > 0.0 {
@@ -144,15 +498,29 @@ fn compute_lighting(scene: &Scene, p: Vec3, n: Vec3, v: Vec3, specular: Option<i
             };
 
             // specular
-            let specular = specular.map_or(0.0, |s| {
-                let r = reflect_ray(-l, n);
-                let r_dot_v = r.dot(v);
-                if r_dot_v > 0.0 {
-                    intensity * (r_dot_v / (r.len() * v.len())).powi(s)
-                } else {
-                    0.0
+            let specular = match specular {
+                None => 0.0,
+                Some(Specular::Phong(s)) => {
+                    let r = reflect_ray(-l, n);
+                    let r_dot_v = r.dot(v);
+                    if r_dot_v > 0.0 {
+                        intensity * (r_dot_v / (r.len() * v.len())).powf(s)
+                    } else {
+                        0.0
+                    }
+                }
+                Some(Specular::BlinnPhong(s)) => {
+                    // the half vector, halfway between the directions to the light and the camera
+                    let h = l / l.len() + v / v.len();
+                    let n_dot_h = n.dot(h);
+                    // no highlights when the light is behind the surface
+                    if n_dot_l > 0.0 && n_dot_h > 0.0 {
+                        intensity * (n_dot_h / (n.len() * h.len())).powf(s)
+                    } else {
+                        0.0
+                    }
                 }
-            });
+            };
 
             i += diffuse + specular;
         }
@@ -169,8 +537,26 @@ fn reflect_ray(r: Vec3, n: Vec3) -> Vec3 {
 mod tests {
     use super::*;
     use crate::approx::ApproxEq;
+    use crate::camera::{FisheyeMapping, Projection};
+    use crate::cuboid::Cuboid;
+    use crate::disk::Disk;
+    use crate::image::Image;
     use crate::scene::Light;
     use crate::scene::Material;
+    use crate::scene::{Geometry, Instance, Object, Shape, Sphere};
+    use crate::texture::{AddressMode, Filter, Texture};
+    use crate::transform::Transform;
+    use std::f64::consts::PI;
+    use std::rc::Rc;
+
+    fn sphere(center: Vec3, radius: f64, material: Material) -> Object {
+        Object {
+            shape: Shape::Sphere(Sphere { center, radius }),
+            material,
+            transform: Transform::IDENTITY,
+            motion: None,
+        }
+    }
 
     #[test]
     fn reflect_ray_works() {
@@ -189,56 +575,316 @@ mod tests {
         assert!(reflect_ray(r, n).approx_eq(Vec3::new(0.0, 0.0, 1.0)));
     }
 
+    #[test]
+    fn go_with_aovs() {
+        // a reflective sphere in front of the camera, which reflects the background, and hides
+        // another sphere
+        let red = Color(1.0, 0.0, 0.0);
+        let blue = Color(0.0, 0.0, 1.0);
+        let scene = Scene {
+            background_color: blue,
+            lights: vec![Light {
+                intensity: 1.0,
+                source: LightSource::Ambient,
+            }],
+            objects: vec![
+                sphere(Vec3::new(0.0, 0.0, 20.0), 1.0, Material::BLACK),
+                sphere(
+                    Vec3::new(0.0, 0.0, 4.0),
+                    2.0,
+                    Material {
+                        color: red,
+                        reflective: 0.5,
+                        ..Material::BLACK
+                    },
+                ),
+            ],
+            instances: vec![],
+            ambient_occlusion: None,
+        };
+        let raytracer = Raytracer {
+            canvas_width: 4,
+            canvas_height: 2,
+            viewport_width: 4.0,
+            viewport_height: 2.0,
+            distance_to_projection_plane: 1.0,
+            camera: Camera::DEFAULT,
+            scene,
+        };
+        let (canvas, aovs) = raytracer.go_with_aovs();
+
+        // the center of the image shows the sphere
+        assert!(aovs.depth.get_pixel(0, 0).unwrap().approx_eq(2.0));
+        let n = aovs.normal.get_pixel(0, 0).unwrap();
+        assert!(n.approx_eq(Vec3::new(0.0, 0.0, -1.0)));
+        assert_eq!(aovs.object.get_pixel(0, 0), Some(Some(1)));
+        assert!(aovs.albedo.get_pixel(0, 0).unwrap().approx_eq(red));
+        assert!(aovs.direct.get_pixel(0, 0).unwrap().approx_eq(0.5 * red));
+        assert!(aovs
+            .reflected
+            .get_pixel(0, 0)
+            .unwrap()
+            .approx_eq(0.5 * blue));
+
+        // the left edge shows the background
+        assert!(aovs.depth.get_pixel(-2, 0).unwrap().is_infinite());
+        assert_eq!(aovs.object.get_pixel(-2, 0), Some(None));
+        assert!(aovs
+            .albedo
+            .get_pixel(-2, 0)
+            .unwrap()
+            .approx_eq(Color::BLACK));
+        assert!(aovs.direct.get_pixel(-2, 0).unwrap().approx_eq(blue));
+        assert!(aovs
+            .reflected
+            .get_pixel(-2, 0)
+            .unwrap()
+            .approx_eq(Color::BLACK));
+
+        // direct and reflected light add up to the image
+        let mut want = Canvas::new(4, 2);
+        for x in -2..2 {
+            for y in -1..1 {
+                let direct = aovs.direct.get_pixel(x, y).unwrap();
+                want.put_pixel(x, y, direct + aovs.reflected.get_pixel(x, y).unwrap());
+            }
+        }
+        assert!(canvas.approx_eq(&want));
+        assert!(canvas.approx_eq(&raytracer.go()));
+    }
+
+    #[test]
+    fn go_with_aovs_fisheye() {
+        // inside a sphere, with a fisheye camera whose image circle doesn't reach the centers of
+        // the corner pixels
+        let raytracer = Raytracer {
+            canvas_width: 2,
+            canvas_height: 2,
+            viewport_width: 2.0,
+            viewport_height: 2.0,
+            distance_to_projection_plane: 1.0,
+            camera: Camera {
+                projection: Projection::Fisheye {
+                    field_of_view: PI,
+                    mapping: FisheyeMapping::Equidistant,
+                },
+                ..Camera::DEFAULT
+            },
+            scene: Scene {
+                background_color: Color::BLACK,
+                lights: vec![],
+                objects: vec![sphere(Vec3::new(0.0, 0.0, 0.0), 5.0, Material::BLACK)],
+                instances: vec![],
+                ambient_occlusion: None,
+            },
+        };
+        let (_, aovs) = raytracer.go_with_aovs();
+
+        // the depth, normal and object index come from the samples closest to the centers
+        for (x, y) in [(-1, -1), (-1, 0), (0, -1), (0, 0)] {
+            assert!(aovs.depth.get_pixel(x, y).unwrap().approx_eq(5.0));
+            assert!(aovs.normal.get_pixel(x, y).unwrap().len().approx_eq(1.0));
+            assert_eq!(aovs.object.get_pixel(x, y), Some(Some(0)));
+        }
+    }
+
     #[test]
     fn closest_intersection_works() {
         // set up scene with two spheres
         let scene = Scene {
             background_color: Color::BLACK,
             lights: vec![],
-            spheres: vec![
-                Sphere {
-                    center: Vec3::new(0.0, 0.0, 3.0),
-                    radius: 1.0,
-                    material: Material::BLACK,
-                },
-                Sphere {
-                    center: Vec3::new(0.0, 0.0, 7.0),
-                    radius: 2.0,
-                    material: Material::BLACK,
-                },
+            objects: vec![
+                sphere(Vec3::new(0.0, 0.0, 3.0), 1.0, Material::BLACK),
+                sphere(Vec3::new(0.0, 0.0, 7.0), 2.0, Material::BLACK),
             ],
+            instances: vec![],
+            ambient_occlusion: None,
         };
 
         // ray doesn't hit any sphere
         let origin = Vec3::new(0.0, 0.0, 0.0);
         let direction = Vec3::new(0.0, 1.0, 0.0);
-        let ray = Ray { origin, direction };
+        let ray = Ray {
+            origin,
+            direction,
+            time: 0.0,
+        };
         assert!(closest_intersection(&scene, ray, 0.0..f64::INFINITY).is_none());
 
         // ray hits the first sphere
         let origin = Vec3::new(0.0, -2.0, 3.0);
         let direction = Vec3::new(0.0, 1.0, 0.0);
-        let ray = Ray { origin, direction };
-        let (sphere, t) = closest_intersection(&scene, ray, 0.0..f64::INFINITY).unwrap();
-        assert_eq!(sphere.radius, 1.0);
-        assert!(t.approx_eq(1.0));
+        let ray = Ray {
+            origin,
+            direction,
+            time: 0.0,
+        };
+        let hit = closest_intersection(&scene, ray, 0.0..f64::INFINITY).unwrap();
+        assert!(hit.t.approx_eq(1.0));
+        assert!(hit.normal.approx_eq(Vec3::new(0.0, -1.0, 0.0)));
 
         // ray hits both spheres, closest_intersection should return the first hit
         let origin = Vec3::new(0.0, 0.0, 0.0);
         let direction = Vec3::new(0.0, 0.0, 1.0);
-        let ray = Ray { origin, direction };
-        let (sphere, t) = closest_intersection(&scene, ray, 0.0..f64::INFINITY).unwrap();
-        assert_eq!(sphere.radius, 1.0);
-        assert!(t.approx_eq(2.0));
+        let ray = Ray {
+            origin,
+            direction,
+            time: 0.0,
+        };
+        let hit = closest_intersection(&scene, ray, 0.0..f64::INFINITY).unwrap();
+        assert!(hit.t.approx_eq(2.0));
+        assert!(hit.normal.approx_eq(Vec3::new(0.0, 0.0, -1.0)));
 
         // ray hits both spheres, but only the hit for sphere 2 is within the range
-        let (sphere, t) = closest_intersection(&scene, ray, 5.0..f64::INFINITY).unwrap();
-        assert_eq!(sphere.radius, 2.0);
-        assert!(t.approx_eq(5.0));
+        let hit = closest_intersection(&scene, ray, 5.0..f64::INFINITY).unwrap();
+        assert!(hit.t.approx_eq(5.0));
+        assert!(hit.normal.approx_eq(Vec3::new(0.0, 0.0, -1.0)));
 
         // ray hits both spheres, but neither is within the range
         assert!(closest_intersection(&scene, ray, 10.0..f64::INFINITY).is_none());
         assert!(closest_intersection(&scene, ray, f64::NEG_INFINITY..1.0).is_none());
+
+        // an instance of the first sphere placed in front of both spheres
+        let geometry = Rc::new(Geometry::new(vec![scene.objects[0].clone()]));
+        let scene = Scene {
+            instances: vec![Instance {
+                geometry,
+                transform: Transform::translation(Vec3::new(0.0, 0.0, -2.0)),
+                material: None,
+            }],
+            ..scene
+        };
+        let hit = closest_intersection(&scene, ray, 0.0..f64::INFINITY).unwrap();
+        assert!(hit.t.approx_eq(0.0));
+        let hit = closest_intersection(&scene, ray, 0.5..f64::INFINITY).unwrap();
+        assert!(hit.t.approx_eq(2.0));
+    }
+
+    #[test]
+    fn ambient_visibility_works() {
+        // a wall that blocks the half of the hemisphere towards +x
+        let wall = Object {
+            shape: Shape::Cuboid(Cuboid {
+                min: Vec3::new(0.01, -10.0, -10.0),
+                max: Vec3::new(10.0, 10.0, 10.0),
+            }),
+            material: Material::BLACK,
+            transform: Transform::IDENTITY,
+            motion: None,
+        };
+        let scene = Scene {
+            background_color: Color::BLACK,
+            lights: vec![],
+            objects: vec![wall],
+            instances: vec![],
+            ambient_occlusion: None,
+        };
+        let p = Vec3::new(0.0, 0.0, 0.0);
+        let n = Vec3::new(0.0, 2.0, 0.0);
+        let settings = |samples, distance| Some(AmbientOcclusion { samples, distance });
+        assert_eq!(ambient_visibility(&scene, None, p, 0.0, n), 1.0);
+        assert_eq!(ambient_visibility(&scene, settings(0, 5.0), p, 0.0, n), 1.0);
+        let visibility = ambient_visibility(&scene, settings(2000, 5.0), p, 0.0, n);
+        assert!(
+            (visibility - 0.5).abs() < 0.05,
+            "visibility == {}",
+            visibility
+        );
+
+        // the wall is too far away to block anything
+        assert_eq!(
+            ambient_visibility(&scene, settings(100, 0.005), p, 0.0, n),
+            1.0
+        );
+
+        // inside a sphere, all ambient light is blocked
+        let scene = Scene {
+            lights: vec![Light {
+                intensity: 0.6,
+                source: LightSource::Ambient,
+            }],
+            objects: vec![sphere(p, 1.0, Material::BLACK)],
+            ambient_occlusion: settings(100, 5.0),
+            ..scene
+        };
+        assert_eq!(
+            ambient_visibility(&scene, settings(100, 5.0), p, 0.0, n),
+            0.0
+        );
+        assert!(compute_lighting(&scene, p, 0.0, n, -n, None, 1.0).approx_eq(0.6));
+        assert!(compute_lighting(&scene, p, 0.0, n, -n, None, 0.25).approx_eq(0.15));
+
+        // a white sphere seen from inside: only camera rays use ambient occlusion
+        let white = Material {
+            color: Color::WHITE,
+            ..Material::BLACK
+        };
+        let scene = Scene {
+            objects: vec![sphere(p, 1.0, white)],
+            ..scene
+        };
+        let ray = Ray {
+            origin: p,
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let hit = closest_intersection(&scene, ray, 0.0..f64::INFINITY).unwrap();
+        let shading = shade(&scene, ray, &hit, true, 0);
+        assert!(shading.direct.approx_eq(Color::BLACK));
+        let shading = shade(&scene, ray, &hit, false, 0);
+        assert!(shading.direct.approx_eq(0.6 * Color::WHITE));
+        let color = trace_ray(&scene, ray, 0.0..f64::INFINITY, 0);
+        assert!(color.approx_eq(0.6 * Color::WHITE));
+    }
+
+    #[test]
+    fn go_ambient_occlusion() {
+        let settings = AmbientOcclusion {
+            samples: 4,
+            distance: 100.0,
+        };
+        let mut raytracer = Raytracer {
+            canvas_width: 4,
+            canvas_height: 2,
+            viewport_width: 1.0,
+            viewport_height: 1.0,
+            distance_to_projection_plane: 1.0,
+            camera: Camera::DEFAULT,
+            scene: Scene {
+                background_color: Color(0.0, 0.0, 1.0),
+                lights: vec![],
+                objects: vec![],
+                instances: vec![],
+                ambient_occlusion: None,
+            },
+        };
+
+        // nothing blocks the light if there's nothing in the scene
+        let mut white = Canvas::new(4, 2);
+        for x in -2..2 {
+            for y in -1..1 {
+                white.put_pixel(x, y, Color::WHITE);
+            }
+        }
+        assert!(raytracer.go_ambient_occlusion(settings).approx_eq(&white));
+
+        // the camera is inside a sphere, which blocks all light
+        raytracer.scene.objects = vec![sphere(Vec3::new(0.0, 0.0, 0.0), 5.0, Material::BLACK)];
+        let image = raytracer.go_ambient_occlusion(settings);
+        assert!(image.approx_eq(&Canvas::new(4, 2)));
+
+        // the rays for a pixel are shared between its 25 samples
+        let per_sample = |samples| {
+            occlusion_per_sample(AmbientOcclusion {
+                samples,
+                ..settings
+            })
+        };
+        assert_eq!(per_sample(100).samples, 4);
+        assert_eq!(per_sample(101).samples, 5);
+        assert_eq!(per_sample(4).samples, 1);
+        assert_eq!(per_sample(0).samples, 0);
     }
 
     #[test]
@@ -251,23 +897,17 @@ mod tests {
                 intensity: 0.8,
                 source: LightSource::Ambient,
             }],
-            spheres: vec![
-                Sphere {
-                    center: Vec3::new(0.0, 0.0, -2.0),
-                    radius: 1.0,
-                    material: Material::BLACK,
-                },
-                Sphere {
-                    center: Vec3::new(0.0, 0.0, 2.0),
-                    radius: 1.0,
-                    material: Material::BLACK,
-                },
+            objects: vec![
+                sphere(Vec3::new(0.0, 0.0, -2.0), 1.0, Material::BLACK),
+                sphere(Vec3::new(0.0, 0.0, 2.0), 1.0, Material::BLACK),
             ],
+            instances: vec![],
+            ambient_occlusion: None,
         };
         let p = Vec3::new(0.0, 0.0, 1.0);
         let n = Vec3::new(0.0, 0.0, -1.0);
         let v = n;
-        assert!(compute_lighting(&scene, p, n, v, None).approx_eq(0.8));
+        assert!(compute_lighting(&scene, p, 0.0, n, v, None, 1.0).approx_eq(0.8));
 
         // only directional light, point is in shadow
         let scene = Scene {
@@ -282,18 +922,21 @@ mod tests {
         let p = Vec3::new(0.0, 0.0, 1.0);
         let n = Vec3::new(0.0, 0.0, 1.0);
         let v = Vec3::new(0.0, 0.0, 1.0);
-        assert!(compute_lighting(&scene, p, n, v, None).approx_eq(0.0));
+        assert!(compute_lighting(&scene, p, 0.0, n, v, None, 1.0).approx_eq(0.0));
 
         // only directional light, camera is facing the back of the object
         let n = Vec3::new(0.0, 0.0, -1.0);
         let p = Vec3::new(0.0, 0.0, 1.0);
-        assert!(compute_lighting(&scene, p, n, v, None).approx_eq(0.0));
+        assert!(compute_lighting(&scene, p, 0.0, n, v, None, 1.0).approx_eq(0.0));
 
         // only directional light
         let n = Vec3::new(0.0, 0.0, 1.0);
         let p = Vec3::new(0.0, 0.0, 3.0);
-        assert!(compute_lighting(&scene, p, n, v, None).approx_eq(0.7));
-        assert!(compute_lighting(&scene, p, n, v, Some(2)).approx_eq(0.7 + 0.7));
+        assert!(compute_lighting(&scene, p, 0.0, n, v, None, 1.0).approx_eq(0.7));
+        assert!(
+            compute_lighting(&scene, p, 0.0, n, v, Some(Specular::Phong(2.0)), 1.0)
+                .approx_eq(0.7 + 0.7)
+        );
 
         // only directional light at a 45 degree angle to the surface
         let scene = Scene {
@@ -307,8 +950,22 @@ mod tests {
         };
         let diffuse = 0.7 / 2f64.sqrt();
         let specular = 0.7 / 2f64;
-        assert!(compute_lighting(&scene, p, n, v, None).approx_eq(diffuse));
-        assert!(compute_lighting(&scene, p, n, v, Some(2)).approx_eq(diffuse + specular));
+        assert!(compute_lighting(&scene, p, 0.0, n, v, None, 1.0).approx_eq(diffuse));
+        assert!(
+            compute_lighting(&scene, p, 0.0, n, v, Some(Specular::Phong(2.0)), 1.0)
+                .approx_eq(diffuse + specular)
+        );
+        let specular = 0.7 * 0.5f64.powf(6.25);
+        assert!(
+            compute_lighting(&scene, p, 0.0, n, v, Some(Specular::Phong(12.5)), 1.0)
+                .approx_eq(diffuse + specular)
+        );
+        // the half vector is at 22.5 degrees to the normal
+        let specular = 0.7 * (PI / 8.0).cos().powi(2);
+        assert!(
+            compute_lighting(&scene, p, 0.0, n, v, Some(Specular::BlinnPhong(2.0)), 1.0)
+                .approx_eq(diffuse + specular)
+        );
 
         // only point light, point is in shadow
         let scene = Scene {
@@ -323,13 +980,16 @@ mod tests {
         let p = Vec3::new(0.0, 0.0, 1.0);
         let n = Vec3::new(0.0, 0.0, 1.0);
         let v = Vec3::new(0.0, 0.0, 1.0);
-        assert!(compute_lighting(&scene, p, n, v, None).approx_eq(0.0));
+        assert!(compute_lighting(&scene, p, 0.0, n, v, None, 1.0).approx_eq(0.0));
 
         // only point light
         let n = Vec3::new(0.0, 0.0, 1.0);
         let p = Vec3::new(0.0, 0.0, 3.0);
-        assert!(compute_lighting(&scene, p, n, v, None).approx_eq(0.7));
-        assert!(compute_lighting(&scene, p, n, v, Some(2)).approx_eq(0.7 + 0.7));
+        assert!(compute_lighting(&scene, p, 0.0, n, v, None, 1.0).approx_eq(0.7));
+        assert!(
+            compute_lighting(&scene, p, 0.0, n, v, Some(Specular::Phong(2.0)), 1.0)
+                .approx_eq(0.7 + 0.7)
+        );
     }
 
     #[test]
@@ -344,32 +1004,33 @@ mod tests {
                 intensity: 0.8,
                 source: LightSource::Ambient,
             }],
-            spheres: vec![
-                Sphere {
-                    center: Vec3::new(0.0, 0.0, -2.0),
-                    radius: 1.0,
-                    material: Material {
+            objects: vec![
+                sphere(
+                    Vec3::new(0.0, 0.0, -2.0),
+                    1.0,
+                    Material {
                         color: green,
-                        specular: None,
-                        reflective: 0.0,
+                        ..Material::BLACK
                     },
-                },
-                Sphere {
-                    center: Vec3::new(0.0, 0.0, 2.0),
-                    radius: 1.0,
-                    material: Material {
+                ),
+                sphere(
+                    Vec3::new(0.0, 0.0, 2.0),
+                    1.0,
+                    Material {
                         color: red,
-                        specular: None,
-                        reflective: 0.0,
+                        ..Material::BLACK
                     },
-                },
+                ),
             ],
+            instances: vec![],
+            ambient_occlusion: None,
         };
 
         // ray doesn't hit anything => background color
         let ray = Ray {
             origin: Vec3::new(0.0, 0.0, 0.0),
             direction: Vec3::new(0.0, 1.0, 0.0),
+            time: 0.0,
         };
         assert!(trace_ray(&scene, ray, 0.0..f64::INFINITY, 2).approx_eq(blue));
 
@@ -377,6 +1038,7 @@ mod tests {
         let ray = Ray {
             origin: Vec3::new(0.0, 0.0, 0.0),
             direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
         };
         assert!(trace_ray(&scene, ray, 0.0..f64::INFINITY, 2).approx_eq(0.8 * red));
 
@@ -384,21 +1046,226 @@ mod tests {
         let ray = Ray {
             origin: Vec3::new(0.0, 0.0, 0.0),
             direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
         };
-        scene.spheres[1].material.reflective = 0.6;
+        scene.objects[1].material.reflective = 0.6;
         assert!(trace_ray(&scene, ray, 0.0..f64::INFINITY, 0).approx_eq(0.8 * red));
 
         // ray hits red sphere, it reflects green sphere => red + green
         let ray = Ray {
             origin: Vec3::new(0.0, 0.0, 0.0),
             direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
         };
         let want = 0.8 * (0.4 * red + 0.6 * green);
         assert!(trace_ray(&scene, ray, 0.0..f64::INFINITY, 1).approx_eq(want));
 
         // both spheres are reflective, recursion depth 2 => red + green + some more red
-        scene.spheres[0].material.reflective = 0.6;
+        scene.objects[0].material.reflective = 0.6;
         let want = 0.8 * (0.4 * red + 0.6 * (0.4 * green + 0.6 * red));
         assert!(dbg!(trace_ray(&scene, ray, 0.0..f64::INFINITY, 2)).approx_eq(dbg!(want)));
     }
+
+    #[test]
+    fn trace_ray_two_sided() {
+        // a disk lit from below, seen from below
+        let white = Material {
+            color: Color::WHITE,
+            ..Material::BLACK
+        };
+        let scene = Scene {
+            background_color: Color::BLACK,
+            lights: vec![Light {
+                intensity: 0.5,
+                source: LightSource::Directional {
+                    direction: Vec3::new(0.0, -1.0, 0.0),
+                },
+            }],
+            objects: vec![Object {
+                shape: Shape::Disk(Disk { radius: 1.0 }),
+                material: white,
+                transform: Transform::IDENTITY,
+                motion: None,
+            }],
+            instances: vec![],
+            ambient_occlusion: None,
+        };
+        let ray = Ray {
+            origin: Vec3::new(0.0, -1.0, -1.0),
+            direction: Vec3::new(0.0, 1.0, 1.0),
+            time: 0.0,
+        };
+        let want = 0.5 * Color::WHITE;
+        assert!(trace_ray(&scene, ray, 0.0..f64::INFINITY, 0).approx_eq(want));
+    }
+
+    #[test]
+    fn trace_ray_glossy() {
+        // a mirror seen from above, reflecting a red sphere on a blue background
+        let red = Color(1.0, 0.0, 0.0);
+        let mut scene = Scene {
+            background_color: Color(0.0, 0.0, 1.0),
+            lights: vec![Light {
+                intensity: 1.0,
+                source: LightSource::Ambient,
+            }],
+            objects: vec![
+                Object {
+                    shape: Shape::Disk(Disk { radius: 10.0 }),
+                    material: Material {
+                        reflective: 1.0,
+                        ..Material::BLACK
+                    },
+                    transform: Transform::IDENTITY,
+                    motion: None,
+                },
+                sphere(
+                    Vec3::new(0.0, 3.0, 0.0),
+                    0.5,
+                    Material {
+                        color: red,
+                        ..Material::BLACK
+                    },
+                ),
+            ],
+            instances: vec![],
+            ambient_occlusion: None,
+        };
+        let ray = Ray {
+            origin: Vec3::new(0.0, 1.0, 0.0),
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        assert!(trace_ray(&scene, ray, 0.0..f64::INFINITY, 1).approx_eq(red));
+
+        // a rough mirror reflects a mix of the sphere and the background
+        scene.objects[0].material.roughness = 0.5;
+        let mut sum = Color::BLACK;
+        for _ in 0..100 {
+            sum += trace_ray(&scene, ray, 0.0..f64::INFINITY, 1);
+        }
+        let Color(r, g, b) = 0.01 * sum;
+        assert!(r > 0.1 && b > 0.1, "{:?}", (r, g, b));
+        assert!((r + g + b).approx_eq(1.0));
+    }
+
+    #[test]
+    fn trace_ray_glossy_samples() {
+        // two parallel rough mirrors facing each other
+        let mirror = |y| Object {
+            shape: Shape::Disk(Disk { radius: 100.0 }),
+            material: Material {
+                reflective: 1.0,
+                roughness: 0.1,
+                ..Material::BLACK
+            },
+            transform: Transform::translation(Vec3::new(0.0, y, 0.0)),
+            motion: None,
+        };
+        let mut scene = Scene {
+            background_color: Color::BLACK,
+            lights: vec![],
+            objects: vec![mirror(0.0), mirror(2.0)],
+            instances: vec![],
+            ambient_occlusion: None,
+        };
+        let ray = Ray {
+            origin: Vec3::new(0.0, 1.0, 0.0),
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        let count_rays = |scene: &Scene, camera_ray| {
+            let hit = closest_intersection(scene, ray, 0.0..f64::INFINITY).unwrap();
+            TRACED_RAYS.with(|n| n.set(0));
+            shade(scene, ray, &hit, camera_ray, 3);
+            TRACED_RAYS.with(|n| n.get())
+        };
+
+        // only the first bounce is split into several rays, each followed by a single ray for the
+        // remaining two bounces
+        assert_eq!(count_rays(&scene, true), 4 * 3);
+        assert_eq!(count_rays(&scene, false), 3);
+        scene.objects[0].material.glossy_samples = 2;
+        assert_eq!(count_rays(&scene, true), 2 * 3);
+    }
+
+    #[test]
+    fn trace_ray_tinted_fresnel() {
+        // a black mirror reflecting a blue background
+        let blue = Color(0.0, 0.0, 1.0);
+        let mut scene = Scene {
+            background_color: blue,
+            lights: vec![],
+            objects: vec![Object {
+                shape: Shape::Disk(Disk { radius: 10.0 }),
+                material: Material {
+                    reflective: 0.5,
+                    ..Material::BLACK
+                },
+                transform: Transform::IDENTITY,
+                motion: None,
+            }],
+            instances: vec![],
+            ambient_occlusion: None,
+        };
+        let head_on = Ray {
+            origin: Vec3::new(0.0, 1.0, 0.0),
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        // at 60 degrees to the normal
+        let oblique = Ray {
+            origin: Vec3::new(0.0, 1.0, 0.0),
+            direction: Vec3::new(0.0, -1.0, 3f64.sqrt()),
+            time: 0.0,
+        };
+        let trace = |scene: &Scene, ray| trace_ray(scene, ray, 0.0..f64::INFINITY, 1);
+        assert!(trace(&scene, head_on).approx_eq(0.5 * blue));
+        assert!(trace(&scene, oblique).approx_eq(0.5 * blue));
+
+        scene.objects[0].material.fresnel = true;
+        assert!(trace(&scene, head_on).approx_eq(0.5 * blue));
+        let r = 0.5 + 0.5 * 0.5f64.powi(5);
+        assert!(trace(&scene, oblique).approx_eq(r * blue));
+
+        scene.objects[0].material.reflection_tint = Color(1.0, 1.0, 0.5);
+        assert!(trace(&scene, head_on).approx_eq(0.25 * blue));
+    }
+
+    #[test]
+    fn trace_ray_textured() {
+        // a disk with the left half red and the right half green, seen from above
+        let (red, green) = (Color(1.0, 0.0, 0.0), Color(0.0, 1.0, 0.0));
+        let image = Image::new(2, 1, vec![red, green]);
+        let scene = Scene {
+            background_color: Color::BLACK,
+            lights: vec![Light {
+                intensity: 1.0,
+                source: LightSource::Ambient,
+            }],
+            objects: vec![Object {
+                shape: Shape::Disk(Disk { radius: 1.0 }),
+                material: Material {
+                    texture: Some(Texture::Image {
+                        image: Rc::new(image),
+                        filter: Filter::Nearest,
+                        address: AddressMode::Wrap,
+                    }),
+                    ..Material::BLACK
+                },
+                transform: Transform::IDENTITY,
+                motion: None,
+            }],
+            instances: vec![],
+            ambient_occlusion: None,
+        };
+        for (x, want) in [(-0.5, red), (0.5, green)] {
+            let ray = Ray {
+                origin: Vec3::new(x, 1.0, 0.0),
+                direction: Vec3::new(0.0, -1.0, 0.0),
+                time: 0.0,
+            };
+            assert!(trace_ray(&scene, ray, 0.0..f64::INFINITY, 0).approx_eq(want));
+        }
+    }
 }
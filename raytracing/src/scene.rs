@@ -1,13 +1,74 @@
+use crate::aabb::Aabb;
 use crate::color::Color;
+use crate::cone::Cone;
+use crate::csg::Csg;
+use crate::cuboid::Cuboid;
+use crate::cylinder::Cylinder;
+use crate::disk::Disk;
+use crate::heightfield::Heightfield;
+use crate::pbr::Pbr;
 use crate::ray::Ray;
+use crate::sdf::SdfShape;
+use crate::texture::{BumpMap, Texture};
+use crate::torus::Torus;
+use crate::transform::Transform;
 use crate::vec3::Vec3;
+use std::f64::consts::PI;
+use std::ops::Range;
+use std::rc::Rc;
 
 /// A scene that can be rendered by a raytracer.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Scene {
     pub background_color: Color,
     pub lights: Vec<Light>,
-    pub spheres: Vec<Sphere>,
+    pub objects: Vec<Object>,
+    pub instances: Vec<Instance>,
+
+    /// If set, ambient light is darkened where nearby objects block it. This only applies to
+    /// surfaces seen directly by the camera, not to reflections.
+    pub ambient_occlusion: Option<AmbientOcclusion>,
+}
+
+/// Settings for ambient occlusion, which darkens ambient light in creases and where objects are
+/// close to each other.
+///
+/// For each point on a surface, random rays are cast into the hemisphere around the surface
+/// normal; the ambient light is scaled by the fraction of the rays that don't hit an object.
+#[derive(Debug, Clone, Copy)]
+pub struct AmbientOcclusion {
+    /// The number of rays for each pixel. They're shared between the pixel's samples for
+    /// anti-aliasing, with at least one ray for each sample.
+    pub samples: usize,
+
+    /// Objects farther away than this don't block ambient light.
+    pub distance: f64,
+}
+
+/// The point where a ray hits the surface of an object.
+#[derive(Debug, Clone, Copy)]
+pub struct Hit<'a> {
+    /// The value `t` for which the ray hits the surface.
+    pub t: f64,
+
+    /// The surface normal at the point. For hits on objects, the normal is in world coordinates
+    /// and normalized.
+    pub normal: Vec3,
+
+    /// The texture coordinates of the point, usually between 0 and 1.
+    pub uv: (f64, f64),
+
+    /// The point in the coordinate system of the shape, for solid textures.
+    pub local_point: Vec3,
+
+    /// The direction in which the texture coordinate `u` increases, which together with the normal
+    /// defines the tangent frame for normal maps. The direction of `v` is `tangent × normal`. The
+    /// tangent isn't normalized, and it may be zero where the texture coordinates are degenerate,
+    /// e.g. at the poles of a sphere.
+    pub tangent: Vec3,
+
+    /// The material of the surface.
+    pub material: &'a Material,
 }
 
 /// A source of (white) light in the scene.
@@ -33,37 +94,397 @@ pub enum LightSource {
     Directional { direction: Vec3 },
 }
 
+/// A model for specular reflection, i.e. the highlights on shiny surfaces.
+///
+/// The parameter is the shininess: higher values mean a shinier surface with smaller highlights.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Specular {
+    /// Phong's model, based on the angle between the reflected light and the direction to the
+    /// camera.
+    Phong(f64),
+
+    /// The Blinn-Phong model, based on the angle between the normal and the half vector between
+    /// the light and the camera. It's cheaper, and more realistic at grazing angles. Its highlights
+    /// are larger than Phong's for the same shininess; about four times the shininess gives
+    /// similar highlights.
+    BlinnPhong(f64),
+}
+
 /// Defines how an object reflects light.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Material {
     /// The object's color.
     pub color: Color,
 
-    /// A parameter to model specular reflection; higher values means a shinier surface.
-    pub specular: Option<i32>,
+    /// If set, the object's color comes from the texture instead of `color`.
+    pub texture: Option<Texture>,
+
+    /// If set, perturbs the surface normals, for bumps and other small details.
+    pub bump: Option<BumpMap>,
+
+    /// If set, the surface is shiny and has specular highlights.
+    pub specular: Option<Specular>,
 
     /// A parameter to model how reflective the surface is; goes from 0.0 (not reflective) to 1.0
     /// (perfect mirror).
     pub reflective: f64,
+
+    /// How rough the reflections are, from 0.0 (sharp, like a mirror) to about 1.0 (very blurry),
+    /// e.g. for brushed metal.
+    pub roughness: f64,
+
+    /// The number of rays averaged for rough reflections of surfaces seen by the camera. Further
+    /// reflections in those reflections use a single ray each, to keep the number of rays from
+    /// growing exponentially with the recursion depth.
+    pub glossy_samples: usize,
+
+    /// The color that reflections are tinted with, e.g. yellow for gold; white leaves them as is.
+    pub reflection_tint: Color,
+
+    /// If set, `reflective` is the reflectivity when looking straight at the surface, and the
+    /// surface becomes more reflective at grazing angles, like glass or water (using Schlick's
+    /// approximation of the Fresnel equations).
+    pub fresnel: bool,
+
+    /// If set, the surface uses the physically based model instead of `specular` and
+    /// `reflective`, with `color` or `texture` as the base color.
+    pub pbr: Option<Pbr>,
 }
 
 impl Material {
     pub const BLACK: Material = Material {
         color: Color::BLACK,
+        texture: None,
+        bump: None,
         specular: None,
         reflective: 0.0,
+        roughness: 0.0,
+        glossy_samples: 4,
+        reflection_tint: Color::WHITE,
+        fresnel: false,
+        pbr: None,
     };
+
+    /// Returns the color of the surface at a hit.
+    pub fn color_at(&self, hit: &Hit) -> Color {
+        match &self.texture {
+            Some(texture) => texture.color_at(hit.uv, hit.local_point),
+            None => self.color,
+        }
+    }
+
+    /// Returns the normal at a hit, with length 1, perturbed by the bump map if there is one.
+    pub fn normal_at(&self, hit: &Hit) -> Vec3 {
+        match &self.bump {
+            Some(bump) => bump.normal_at(hit.normal, hit.tangent, hit.uv, hit.local_point),
+            None => hit.normal.normalized(),
+        }
+    }
+}
+
+/// An object in a scene: a shape with a material, placed in the scene with a transformation.
+///
+/// The shape is defined in the object's local coordinate system; `transform` maps it to world
+/// coordinates. This allows for shapes that can't be described directly, e.g. a sphere with a
+/// scaling transformation is an ellipsoid.
+#[derive(Debug, Clone)]
+pub struct Object {
+    pub shape: Shape,
+    pub material: Material,
+    pub transform: Transform,
+
+    /// If set, the object moves while the camera's shutter is open, which blurs it.
+    pub motion: Option<Motion>,
 }
 
-/// A sphere in a scene.
+/// How an object moves over time, for motion blur. Time is measured in frames, from 0 to 1 (see
+/// `Ray::time`).
+#[derive(Debug, Clone, Copy)]
+pub enum Motion {
+    /// Moves in a straight line, by `velocity` per frame. Like `Object::transform`, the velocity is
+    /// in the coordinate system the object is placed in, e.g. world coordinates for objects in a
+    /// scene.
+    Linear { velocity: Vec3 },
+}
+
+impl Motion {
+    /// Returns how far the object has moved from its starting position at time `time`.
+    pub fn offset(&self, time: f64) -> Vec3 {
+        match self {
+            Motion::Linear { velocity } => time * *velocity,
+        }
+    }
+}
+
+impl Object {
+    /// Returns all points where the line through the ray hits the object, sorted by `t`.
+    ///
+    /// The ray is transformed into the object's local coordinate system, so the values `t` are the
+    /// same in local and world coordinates. The hits include negative values of `t`, i.e. points
+    /// behind the ray's origin.
+    pub fn hits(&self, ray: Ray) -> Vec<Hit<'_>> {
+        // a moving object is hit where the ray, moved back by the object's motion, hits the object
+        // at its starting position
+        let ray = match self.motion {
+            Some(motion) => Ray {
+                origin: ray.origin - motion.offset(ray.time),
+                ..ray
+            },
+            None => ray,
+        };
+        let mut hits = self
+            .shape
+            .hits(self.transform.inverse_ray(ray), &self.material);
+        for hit in &mut hits {
+            hit.normal = self.transform.normal(hit.normal);
+            hit.tangent = self.transform.vector(hit.tangent);
+        }
+        hits
+    }
+
+    /// Return the values `t` where the ray intersects the object, in a sorted vector.
+    pub fn intersect_ray(&self, ray: Ray) -> Vec<f64> {
+        self.hits(ray).iter().map(|hit| hit.t).collect()
+    }
+
+    /// Returns the hit with the smallest `t` in the given range, if there is one.
+    pub fn closest_hit(&self, ray: Ray, t_range: &Range<f64>) -> Option<Hit<'_>> {
+        self.hits(ray)
+            .into_iter()
+            .find(|hit| t_range.contains(&hit.t))
+    }
+
+    /// Returns a box that contains the object, in world coordinates. For moving objects, the box
+    /// contains the object at all times from 0 to 1.
+    pub fn bounds(&self) -> Aabb {
+        let bounds = self.shape.bounds().transformed(&self.transform);
+        match self.motion {
+            Some(motion) => {
+                let end = Transform::translation(motion.offset(1.0));
+                bounds.union(bounds.transformed(&end))
+            }
+            None => bounds,
+        }
+    }
+}
+
+/// A collection of objects that can be shared by several instances.
+///
+/// The objects are defined in the geometry's own coordinate system, and each instance places
+/// them in the scene with its own transformation.
+#[derive(Debug)]
+pub struct Geometry {
+    objects: Vec<Object>,
+    bounds: Aabb,
+}
+
+impl Geometry {
+    /// Returns a geometry with the given objects.
+    pub fn new(objects: Vec<Object>) -> Geometry {
+        let bounds = objects
+            .iter()
+            .fold(Aabb::EMPTY, |acc, object| acc.union(object.bounds()));
+        Geometry { objects, bounds }
+    }
+
+    /// Returns the objects that make up the geometry.
+    pub fn objects(&self) -> &[Object] {
+        &self.objects
+    }
+
+    /// Returns a box that contains all the objects.
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    /// Returns the hit with the smallest `t` in the given range, if there is one.
+    pub fn closest_hit(&self, ray: Ray, t_range: &Range<f64>) -> Option<Hit<'_>> {
+        let (t0, t1) = self.bounds.intersect_ray(ray)?;
+        if t1 < t_range.start || t0 >= t_range.end {
+            return None;
+        }
+        self.objects
+            .iter()
+            .filter_map(|object| object.closest_hit(ray, t_range))
+            .min_by(|a, b| a.t.total_cmp(&b.t))
+    }
+}
+
+/// A copy of a shared geometry, placed in the scene with its own transformation.
+///
+/// Many instances can refer to the same geometry, so memory use doesn't grow with the number of
+/// copies. Rays are first tested against the geometry's bounding box in the instance's coordinate
+/// system, so instances that a ray misses are skipped without looking at their objects.
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub geometry: Rc<Geometry>,
+    pub transform: Transform,
+
+    /// If set, this material is used for all objects instead of their own materials.
+    pub material: Option<Material>,
+}
+
+impl Instance {
+    /// Returns the hit with the smallest `t` in the given range, if there is one.
+    pub fn closest_hit(&self, ray: Ray, t_range: &Range<f64>) -> Option<Hit<'_>> {
+        let hit = self
+            .geometry
+            .closest_hit(self.transform.inverse_ray(ray), t_range)?;
+        Some(Hit {
+            t: hit.t,
+            normal: self.transform.normal(hit.normal),
+            uv: hit.uv,
+            local_point: hit.local_point,
+            tangent: self.transform.vector(hit.tangent),
+            material: self.material.as_ref().unwrap_or(hit.material),
+        })
+    }
+
+    /// Returns a box that contains the instance, in world coordinates.
+    pub fn bounds(&self) -> Aabb {
+        self.geometry.bounds().transformed(&self.transform)
+    }
+}
+
+/// The geometric shape of an object, in the object's local coordinate system.
+#[derive(Debug, Clone)]
+pub enum Shape {
+    Sphere(Sphere),
+    Cuboid(Cuboid),
+    Cylinder(Cylinder),
+    Cone(Cone),
+    Disk(Disk),
+    Torus(Torus),
+    Heightfield(Heightfield),
+    Sdf(SdfShape),
+    Csg(Csg),
+}
+
+impl Shape {
+    /// Returns all points where the line through the ray hits the shape, sorted by `t`.
+    ///
+    /// The normals are in the shape's coordinate system and not necessarily normalized. For closed
+    /// shapes, the hits alternate between entering and leaving the shape.
+    pub fn hits<'a>(&'a self, ray: Ray, material: &'a Material) -> Vec<Hit<'a>> {
+        match self {
+            Shape::Sphere(sphere) => surface_hits(sphere.intersect_ray(ray), ray, material, |p| {
+                (sphere.normal_at(p), sphere.uv_at(p), sphere.tangent_at(p))
+            }),
+            Shape::Cuboid(cuboid) => surface_hits(cuboid.intersect_ray(ray), ray, material, |p| {
+                (cuboid.normal_at(p), cuboid.uv_at(p), cuboid.tangent_at(p))
+            }),
+            Shape::Cylinder(cylinder) => {
+                surface_hits(cylinder.intersect_ray(ray), ray, material, |p| {
+                    (
+                        cylinder.normal_at(p),
+                        cylinder.uv_at(p),
+                        cylinder.tangent_at(p),
+                    )
+                })
+            }
+            Shape::Cone(cone) => surface_hits(cone.intersect_ray(ray), ray, material, |p| {
+                (cone.normal_at(p), cone.uv_at(p), cone.tangent_at(p))
+            }),
+            Shape::Disk(disk) => surface_hits(disk.intersect_ray(ray), ray, material, |p| {
+                (disk.normal_at(p), disk.uv_at(p), disk.tangent_at(p))
+            }),
+            Shape::Torus(torus) => surface_hits(torus.intersect_ray(ray), ray, material, |p| {
+                (torus.normal_at(p), torus.uv_at(p), torus.tangent_at(p))
+            }),
+            Shape::Heightfield(heightfield) => {
+                surface_hits(heightfield.intersect_ray(ray), ray, material, |p| {
+                    (
+                        heightfield.normal_at(p),
+                        heightfield.uv_at(p),
+                        heightfield.tangent_at(p),
+                    )
+                })
+            }
+            Shape::Sdf(sdf) => surface_hits(sdf.intersect_ray(ray), ray, material, |p| {
+                (sdf.normal_at(p), sdf.uv_at(p), sdf.tangent_at(p))
+            }),
+            Shape::Csg(csg) => csg.hits(ray),
+        }
+    }
+
+    /// Returns a box that contains the shape.
+    pub fn bounds(&self) -> Aabb {
+        match self {
+            Shape::Sphere(sphere) => sphere.bounds(),
+            Shape::Cuboid(cuboid) => cuboid.bounds(),
+            Shape::Cylinder(cylinder) => cylinder.bounds(),
+            Shape::Cone(cone) => cone.bounds(),
+            Shape::Disk(disk) => disk.bounds(),
+            Shape::Torus(torus) => torus.bounds(),
+            Shape::Heightfield(heightfield) => heightfield.bounds(),
+            Shape::Sdf(sdf) => sdf.bounds(),
+            Shape::Csg(csg) => csg.bounds(),
+        }
+    }
+}
+
+/// Returns hits for the values `t` where a ray intersects a shape, with the normals, texture
+/// coordinates, and tangents given by `surface_at`.
+fn surface_hits(
+    ts: Vec<f64>,
+    ray: Ray,
+    material: &Material,
+    surface_at: impl Fn(Vec3) -> (Vec3, (f64, f64), Vec3),
+) -> Vec<Hit<'_>> {
+    ts.into_iter()
+        .map(|t| {
+            let p = ray.at(t);
+            let (normal, uv, tangent) = surface_at(p);
+            Hit {
+                t,
+                normal,
+                uv,
+                local_point: p,
+                tangent,
+                material,
+            }
+        })
+        .collect()
+}
+
+/// A sphere.
 #[derive(Debug, Clone, Copy)]
 pub struct Sphere {
     pub center: Vec3,
     pub radius: f64,
-    pub material: Material,
 }
 
 impl Sphere {
+    /// Returns the surface normal at point `p`, which must be on the surface of the sphere.
+    pub fn normal_at(&self, p: Vec3) -> Vec3 {
+        (p - self.center) / self.radius
+    }
+
+    /// Returns the texture coordinates at point `p`: `u` is the longitude, starting at the back
+    /// and increasing eastward, and `v` is the latitude, from the south pole to the north pole.
+    pub fn uv_at(&self, p: Vec3) -> (f64, f64) {
+        let n = self.normal_at(p);
+        let u = 0.5 + n.x.atan2(-n.z) / (2.0 * PI);
+        let v = 0.5 + n.y.clamp(-1.0, 1.0).asin() / PI;
+        (u, v)
+    }
+
+    /// Returns the direction in which `u` increases at point `p`, which must be on the surface of
+    /// the sphere. It's zero at the poles.
+    pub fn tangent_at(&self, p: Vec3) -> Vec3 {
+        let d = p - self.center;
+        Vec3::new(-d.z, 0.0, d.x)
+    }
+
+    /// Returns a box that contains the sphere.
+    pub fn bounds(&self) -> Aabb {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb {
+            min: self.center - r,
+            max: self.center + r,
+        }
+    }
+
     /// Return the values `t` where the ray intersects the sphere.
     ///
     /// Returns 0 to 2 values in a sorted vector.
@@ -75,44 +496,154 @@ impl Sphere {
         let b = 2.0 * co.dot(ray.direction);
         let c = co.dot(co) - r * r;
 
-        let discriminant = b * b - 4.0 * a * c;
-        if discriminant < 0.0 {
-            return vec![];
-        }
+        solve_quadratic(a, b, c)
+    }
+}
+
+/// Returns the real roots of `a*x^2 + b*x + c`, in a sorted vector.
+///
+/// Returns 0 to 2 values; a double root is returned once. If `a` is zero, the equation is solved
+/// as a linear equation instead. The roots are computed in a way that avoids cancellation when `b`
+/// is large compared to `a*c`.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a == 0.0 {
+        return if b == 0.0 { vec![] } else { vec![-c / b] };
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return vec![];
+    }
+
+    if discriminant == 0.0 {
+        return vec![-b / (2.0 * a)];
+    }
+
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let t1 = q / a;
+    let t2 = c / q;
+    if t1 < t2 {
+        vec![t1, t2]
+    } else {
+        vec![t2, t1]
+    }
+}
+
+/// Returns the real roots of `a*x^4 + b*x^3 + c*x^2 + d*x + e`, in a sorted vector.
+///
+/// See `solve_polynomial` for how the roots are found.
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    solve_polynomial(&[a, b, c, d, e])
+}
 
-        if discriminant == 0.0 {
-            return vec![-b / (2.0 * a)];
+/// Returns the real roots of the polynomial with the given coefficients (highest degree first),
+/// in a sorted vector.
+///
+/// Rather than using closed-form solutions for higher degrees, which are prone to cancellation,
+/// this finds the roots of the derivative first (recursively, down to the quadratic case). Between
+/// two consecutive roots of the derivative, the polynomial is monotonic, so it has at most one
+/// root there, which is found with safeguarded Newton iteration. Roots where the polynomial only
+/// touches zero without changing sign are found only if the value there is exactly zero.
+pub fn solve_polynomial(coefficients: &[f64]) -> Vec<f64> {
+    let first = coefficients
+        .iter()
+        .position(|&c| c != 0.0)
+        .unwrap_or(coefficients.len());
+    let coefficients = &coefficients[first..];
+    match coefficients.len() {
+        0 | 1 => return vec![],
+        2 => return vec![-coefficients[1] / coefficients[0]],
+        3 => return solve_quadratic(coefficients[0], coefficients[1], coefficients[2]),
+        _ => {}
+    }
+
+    // all roots are within the Cauchy bound
+    let bound = 1.0
+        + coefficients[1..]
+            .iter()
+            .map(|c| (c / coefficients[0]).abs())
+            .fold(0.0, f64::max);
+    let n = coefficients.len() - 1;
+    let derivative: Vec<f64> = coefficients[..n]
+        .iter()
+        .enumerate()
+        .map(|(i, c)| c * (n - i) as f64)
+        .collect();
+    let mut points = vec![-bound];
+    points.extend(
+        solve_polynomial(&derivative)
+            .into_iter()
+            .filter(|x| x.abs() < bound),
+    );
+    points.push(bound);
+
+    let mut roots = vec![];
+    for pair in points.windows(2) {
+        let (lo, hi) = (pair[0], pair[1]);
+        let (p_lo, p_hi) = (evaluate(coefficients, lo), evaluate(coefficients, hi));
+        if p_lo == 0.0 {
+            if roots.last() != Some(&lo) {
+                roots.push(lo);
+            }
+        } else if p_hi == 0.0 {
+            roots.push(hi);
+        } else if p_lo.signum() != p_hi.signum() {
+            roots.push(find_root(coefficients, &derivative, lo, hi, p_lo));
         }
+    }
+    roots
+}
+
+/// Evaluates the polynomial with the given coefficients at `x`, using Horner's method.
+fn evaluate(coefficients: &[f64], x: f64) -> f64 {
+    coefficients.iter().fold(0.0, |acc, c| acc * x + c)
+}
 
-        let t1 = (-b + discriminant.sqrt()) / (2.0 * a);
-        let t2 = (-b - discriminant.sqrt()) / (2.0 * a);
-        if t1 < t2 {
-            vec![t1, t2]
+/// Finds the root of a polynomial in `[lo, hi]`, where it's monotonic and changes sign, with
+/// Newton's method, falling back to bisection when a Newton step leaves the interval.
+fn find_root(coefficients: &[f64], derivative: &[f64], lo: f64, hi: f64, p_lo: f64) -> f64 {
+    let (mut lo, mut hi) = (lo, hi);
+    let mut x = 0.5 * (lo + hi);
+    for _ in 0..100 {
+        let p = evaluate(coefficients, x);
+        if p == 0.0 {
+            return x;
+        }
+        if p.signum() == p_lo.signum() {
+            lo = x;
         } else {
-            vec![t2, t1]
+            hi = x;
+        }
+        let newton = x - p / evaluate(derivative, x);
+        let next = if newton > lo && newton < hi {
+            newton
+        } else {
+            0.5 * (lo + hi)
+        };
+        if (next - x).abs() <= f64::EPSILON * x.abs().max(1.0) {
+            return next;
         }
+        x = next;
     }
+    x
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::approx::ApproxEq;
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
 
     #[test]
     fn sphere_intersect_ray() {
         let ray = Ray {
             origin: Vec3::new(0.0, 0.0, 0.0),
             direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
         };
         let sphere = Sphere {
             center: Vec3::new(0.0, 0.0, 3.0),
             radius: 1.0,
-            material: Material {
-                color: Color::BLACK,
-                specular: None,
-                reflective: 0.0,
-            },
         };
 
         // simple case
@@ -182,4 +713,319 @@ mod tests {
         let want = vec![1.012995902197277, 1.949967060765686];
         assert!(sphere.intersect_ray(new_direction).approx_eq(&want));
     }
+
+    #[test]
+    fn object_intersect_ray() {
+        let ray = Ray {
+            origin: Vec3::new(0.0, 0.0, 0.0),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let sphere = Object {
+            shape: Shape::Sphere(Sphere {
+                center: Vec3::new(0.0, 0.0, 0.0),
+                radius: 1.0,
+            }),
+            material: Material::BLACK,
+            transform: Transform::translation(Vec3::new(0.0, 0.0, 3.0)),
+            motion: None,
+        };
+        let want = vec![2.0, 4.0];
+        assert!(sphere.intersect_ray(ray).approx_eq(&want));
+
+        // stretched along the z axis
+        let ellipsoid = Object {
+            transform: sphere.transform * Transform::scaling(Vec3::new(1.0, 1.0, 2.0)).unwrap(),
+            ..sphere.clone()
+        };
+        let want = vec![1.0, 5.0];
+        assert!(ellipsoid.intersect_ray(ray).approx_eq(&want));
+
+        // squashed along the x axis, then rotated so the ray goes along the short axis
+        let rotated = Object {
+            transform: sphere.transform
+                * Transform::rotation(Vec3::new(0.0, 1.0, 0.0), FRAC_PI_2)
+                * Transform::scaling(Vec3::new(0.5, 1.0, 1.0)).unwrap(),
+            ..sphere
+        };
+        let want = vec![2.5, 3.5];
+        assert!(rotated.intersect_ray(ray).approx_eq(&want));
+    }
+
+    #[test]
+    fn sphere_uv_at() {
+        let sphere = Sphere {
+            center: Vec3::new(0.0, 0.0, 3.0),
+            radius: 2.0,
+        };
+        let cases = [
+            (Vec3::new(0.0, 0.0, 1.0), (0.5, 0.5)),
+            (Vec3::new(0.0, 0.0, 5.0), (1.0, 0.5)),
+            (Vec3::new(-2.0, 0.0, 3.0), (0.25, 0.5)),
+            (Vec3::new(0.0, 2f64.sqrt(), 3.0 - 2f64.sqrt()), (0.5, 0.75)),
+            (Vec3::new(0.0, -2f64.sqrt(), 3.0 - 2f64.sqrt()), (0.5, 0.25)),
+        ];
+        for (p, want) in cases {
+            let got = sphere.uv_at(p);
+            assert!(got.approx_eq(want), "uv_at({:?}) == {:?}", p, got);
+        }
+    }
+
+    #[test]
+    fn sphere_tangent_at() {
+        let sphere = Sphere {
+            center: Vec3::new(0.0, 0.0, 3.0),
+            radius: 2.0,
+        };
+        // perpendicular to the normal, and in the direction in which u increases
+        for p in [
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(-2.0, 0.0, 3.0),
+            Vec3::new(0.0, 2f64.sqrt(), 3.0 - 2f64.sqrt()),
+        ] {
+            let tangent = sphere.tangent_at(p);
+            assert!(tangent.dot(sphere.normal_at(p)).approx_eq(0.0));
+            let (u, v) = sphere.uv_at(p);
+            let (u1, v1) = sphere.uv_at(p + 1E-3 * tangent);
+            assert!(u1 > u && (v1 - v).abs() < 1E-5, "tangent_at({:?})", p);
+        }
+        assert!(sphere
+            .tangent_at(Vec3::new(0.0, 2.0, 3.0))
+            .approx_eq(Vec3::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn object_hits() {
+        let ellipsoid = Object {
+            shape: Shape::Sphere(Sphere {
+                center: Vec3::new(0.0, 0.0, 0.0),
+                radius: 1.0,
+            }),
+            material: Material::BLACK,
+            transform: Transform::scaling(Vec3::new(2.0, 1.0, 1.0)).unwrap(),
+            motion: None,
+        };
+        let ray = Ray {
+            origin: Vec3::new(5.0, 0.0, 0.0),
+            direction: Vec3::new(-1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let hits = ellipsoid.hits(ray);
+        assert_eq!(hits.len(), 2);
+        assert!(hits[0].t.approx_eq(3.0));
+        assert!(hits[0].normal.approx_eq(Vec3::new(1.0, 0.0, 0.0)));
+        assert!(hits[0].uv.approx_eq((0.75, 0.5)));
+        assert!(hits[1].t.approx_eq(7.0));
+        assert!(hits[1].normal.approx_eq(Vec3::new(-1.0, 0.0, 0.0)));
+        assert!(hits[1].uv.approx_eq((0.25, 0.5)));
+
+        // on the ellipse x^2/4 + y^2 = 1, the normal is proportional to (x/4, y)
+        let (s, c) = FRAC_PI_4.sin_cos();
+        let ray = Ray {
+            origin: Vec3::new(0.0, 0.0, 0.0),
+            direction: Vec3::new(2.0 * c, s, 0.0),
+            time: 0.0,
+        };
+        let hits = ellipsoid.hits(ray);
+        assert_eq!(hits.len(), 2);
+        assert!(hits[1].t.approx_eq(1.0));
+        assert!(hits[1]
+            .normal
+            .approx_eq(Vec3::new(c / 2.0, s, 0.0).normalized()));
+    }
+
+    #[test]
+    fn moving_object_hits() {
+        let object = Object {
+            motion: Some(Motion::Linear {
+                velocity: Vec3::new(0.0, 0.0, 4.0),
+            }),
+            ..unit_sphere(Material::BLACK)
+        };
+        let ray = |time| Ray {
+            origin: Vec3::new(0.0, 0.0, -5.0),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            time,
+        };
+        let t = |time| object.hits(ray(time))[0].t;
+        assert!(t(0.0).approx_eq(4.0));
+        assert!(t(0.5).approx_eq(6.0));
+        assert!(t(1.0).approx_eq(8.0));
+
+        let bounds = object.bounds();
+        assert!(bounds.min.approx_eq(Vec3::new(-1.0, -1.0, -1.0)));
+        assert!(bounds.max.approx_eq(Vec3::new(1.0, 1.0, 5.0)));
+    }
+
+    fn unit_sphere(material: Material) -> Object {
+        Object {
+            shape: Shape::Sphere(Sphere {
+                center: Vec3::new(0.0, 0.0, 0.0),
+                radius: 1.0,
+            }),
+            material,
+            transform: Transform::IDENTITY,
+            motion: None,
+        }
+    }
+
+    #[test]
+    fn object_closest_hit() {
+        let object = Object {
+            transform: Transform::translation(Vec3::new(0.0, 0.0, 3.0)),
+            ..unit_sphere(Material::BLACK)
+        };
+        let ray = Ray {
+            origin: Vec3::new(0.0, 0.0, 0.0),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let hit = object.closest_hit(ray, &(0.0..f64::INFINITY)).unwrap();
+        assert!(hit.t.approx_eq(2.0));
+        assert!(hit.normal.approx_eq(Vec3::new(0.0, 0.0, -1.0)));
+        let hit = object.closest_hit(ray, &(3.0..f64::INFINITY)).unwrap();
+        assert!(hit.t.approx_eq(4.0));
+        assert!(hit.normal.approx_eq(Vec3::new(0.0, 0.0, 1.0)));
+        assert!(object.closest_hit(ray, &(0.0..1.0)).is_none());
+    }
+
+    #[test]
+    fn instance_closest_hit() {
+        let red = Material {
+            color: Color(1.0, 0.0, 0.0),
+            ..Material::BLACK
+        };
+        let green = Material {
+            color: Color(0.0, 1.0, 0.0),
+            ..Material::BLACK
+        };
+        let geometry = Rc::new(Geometry::new(vec![
+            unit_sphere(red.clone()),
+            Object {
+                transform: Transform::translation(Vec3::new(0.0, 0.0, 2.0)),
+                ..unit_sphere(red.clone())
+            },
+        ]));
+        assert!(geometry.bounds().min.approx_eq(Vec3::new(-1.0, -1.0, -1.0)));
+        assert!(geometry.bounds().max.approx_eq(Vec3::new(1.0, 1.0, 3.0)));
+
+        // two copies of the geometry, one with a material override
+        let first = Instance {
+            geometry: Rc::clone(&geometry),
+            transform: Transform::translation(Vec3::new(0.0, 0.0, 5.0)),
+            material: None,
+        };
+        let second = Instance {
+            geometry: Rc::clone(&geometry),
+            transform: Transform::translation(Vec3::new(3.0, 0.0, 5.0))
+                * Transform::scaling(Vec3::new(0.5, 0.5, 0.5)).unwrap(),
+            material: Some(green.clone()),
+        };
+        assert!(second.bounds().min.approx_eq(Vec3::new(2.5, -0.5, 4.5)));
+        assert!(second.bounds().max.approx_eq(Vec3::new(3.5, 0.5, 6.5)));
+
+        let t_range = 0.0..f64::INFINITY;
+        let ray = Ray {
+            origin: Vec3::new(0.0, 0.0, 0.0),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let hit = first.closest_hit(ray, &t_range).unwrap();
+        assert!(hit.t.approx_eq(4.0));
+        assert!(hit.material.color.approx_eq(red.color));
+        assert!(second.closest_hit(ray, &t_range).is_none());
+
+        let ray = Ray {
+            origin: Vec3::new(3.0, 0.0, 0.0),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let hit = second.closest_hit(ray, &t_range).unwrap();
+        assert!(hit.t.approx_eq(4.5));
+        assert!(hit.normal.approx_eq(Vec3::new(0.0, 0.0, -1.0)));
+        assert!(hit.material.color.approx_eq(green.color));
+
+        // the second sphere in the geometry, from above
+        let ray = Ray {
+            origin: Vec3::new(3.0, 2.0, 6.0),
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        let hit = second.closest_hit(ray, &t_range).unwrap();
+        assert!(hit.t.approx_eq(1.5));
+        assert!(hit.normal.approx_eq(Vec3::new(0.0, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn solve_quadratic_works() {
+        // (x - 2)(x + 3)
+        assert!(solve_quadratic(1.0, 1.0, -6.0).approx_eq(&vec![-3.0, 2.0]));
+        // 2(x - 1)(x - 4)
+        assert!(solve_quadratic(2.0, -10.0, 8.0).approx_eq(&vec![1.0, 4.0]));
+        // (x - 1)^2
+        assert!(solve_quadratic(1.0, -2.0, 1.0).approx_eq(&vec![1.0]));
+        // x^2 + 1
+        assert!(solve_quadratic(1.0, 0.0, 1.0).is_empty());
+        // x^2 - 4, b is zero
+        assert!(solve_quadratic(1.0, 0.0, -4.0).approx_eq(&vec![-2.0, 2.0]));
+        // linear: 2x - 3
+        assert!(solve_quadratic(0.0, 2.0, -3.0).approx_eq(&vec![1.5]));
+        assert!(solve_quadratic(0.0, 0.0, 1.0).is_empty());
+    }
+
+    #[test]
+    fn solve_quadratic_is_stable() {
+        // roots 1e-9 and 1e9; the naive formula loses the small root to cancellation
+        let roots = solve_quadratic(1.0, -(1e9 + 1e-9), 1.0);
+        assert_eq!(roots.len(), 2);
+        assert!((roots[0] - 1e-9).abs() < 1e-20);
+        assert!((roots[1] / 1e9 - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn solve_quartic_works() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        let roots = solve_quartic(1.0, -10.0, 35.0, -50.0, 24.0);
+        assert!(
+            roots.approx_eq(&vec![1.0, 2.0, 3.0, 4.0]),
+            "got {:?}",
+            roots
+        );
+        // 2(x + 0.5)(x - 0.25)(x^2 + 1)
+        let roots = solve_quartic(2.0, 0.5, 1.75, 0.5, -0.25);
+        assert!(roots.approx_eq(&vec![-0.5, 0.25]), "got {:?}", roots);
+        // x^4 + 1
+        assert!(solve_quartic(1.0, 0.0, 0.0, 0.0, 1.0).is_empty());
+        // x^4 - x^2 = x^2 (x - 1)(x + 1), with a double root at 0
+        let roots = solve_quartic(1.0, 0.0, -1.0, 0.0, 0.0);
+        assert!(roots.approx_eq(&vec![-1.0, 0.0, 1.0]), "got {:?}", roots);
+        // leading zero coefficients reduce the degree
+        let roots = solve_quartic(0.0, 0.0, 1.0, 1.0, -6.0);
+        assert!(roots.approx_eq(&vec![-3.0, 2.0]), "got {:?}", roots);
+    }
+
+    #[test]
+    fn solve_polynomial_close_roots() {
+        // (x - 1)(x - 1.001)(x + 100)(x - 1000)
+        let want = vec![-100.0, 1.0, 1.001, 1000.0];
+        let mut coefficients = vec![1.0];
+        for r in &want {
+            // multiply by (x - r)
+            let mut next = coefficients.clone();
+            next.push(0.0);
+            for (i, c) in coefficients.iter().enumerate() {
+                next[i + 1] -= r * c;
+            }
+            coefficients = next;
+        }
+        let roots = solve_polynomial(&coefficients);
+        assert_eq!(roots.len(), 4, "got {:?}", roots);
+        for (got, want) in roots.iter().zip(want.iter()) {
+            assert!(
+                (got - want).abs() < 1e-9 * want.abs().max(1.0),
+                "got {:?}",
+                roots
+            );
+        }
+    }
 }
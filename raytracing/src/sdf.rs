@@ -0,0 +1,427 @@
+//! Defines shapes described by signed distance functions and rendered by sphere tracing.
+
+use crate::aabb::Aabb;
+use crate::cuboid::box_mapping;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// A signed distance function: for each point, it gives the distance to the closest point on the
+/// surface, negative inside the shape.
+///
+/// The smooth combinations and the repetition don't give exact distances, but they never
+/// overestimate the distance, which is all that sphere tracing needs.
+#[derive(Debug, Clone)]
+pub enum Sdf {
+    /// A sphere centered on the origin.
+    Sphere { radius: f64 },
+
+    /// A box centered on the origin, with the given size along each axis.
+    Box { size: Vec3 },
+
+    /// A torus around the y axis, centered on the origin.
+    Torus {
+        major_radius: f64,
+        minor_radius: f64,
+    },
+
+    /// All points within `radius` of the line segment from `a` to `b`.
+    Capsule { a: Vec3, b: Vec3, radius: f64 },
+
+    /// A shape moved by `offset`.
+    Translate { offset: Vec3, sdf: Box<Sdf> },
+
+    /// The union of two shapes, with the seam rounded over a distance of about `k`.
+    SmoothUnion { a: Box<Sdf>, b: Box<Sdf>, k: f64 },
+
+    /// Shape `a` with shape `b` cut out of it, with the edges rounded over a distance of about
+    /// `k`.
+    SmoothSubtraction { a: Box<Sdf>, b: Box<Sdf>, k: f64 },
+
+    /// Infinitely many copies of a shape, repeated with the given period along each axis. A
+    /// period of zero means the shape isn't repeated along that axis. The shape should fit into
+    /// one period.
+    Repeat { period: Vec3, sdf: Box<Sdf> },
+}
+
+impl Sdf {
+    /// Returns the signed distance from point `p` to the surface.
+    pub fn distance(&self, p: Vec3) -> f64 {
+        match self {
+            Sdf::Sphere { radius } => p.len() - radius,
+            Sdf::Box { size } => {
+                let q = Vec3::new(
+                    p.x.abs() - 0.5 * size.x,
+                    p.y.abs() - 0.5 * size.y,
+                    p.z.abs() - 0.5 * size.z,
+                );
+                let outside = Vec3::new(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0)).len();
+                let inside = q.x.max(q.y).max(q.z).min(0.0);
+                outside + inside
+            }
+            Sdf::Torus {
+                major_radius,
+                minor_radius,
+            } => {
+                let ring = (p.x * p.x + p.z * p.z).sqrt() - major_radius;
+                (ring * ring + p.y * p.y).sqrt() - minor_radius
+            }
+            Sdf::Capsule { a, b, radius } => {
+                let pa = p - *a;
+                let ba = *b - *a;
+                let h = (pa.dot(ba) / ba.dot(ba)).clamp(0.0, 1.0);
+                (pa - ba * h).len() - radius
+            }
+            Sdf::Translate { offset, sdf } => sdf.distance(p - *offset),
+            Sdf::SmoothUnion { a, b, k } => {
+                let (a, b) = (a.distance(p), b.distance(p));
+                if *k <= 0.0 {
+                    return a.min(b);
+                }
+                let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+                mix(b, a, h) - k * h * (1.0 - h)
+            }
+            Sdf::SmoothSubtraction { a, b, k } => {
+                let (a, b) = (a.distance(p), b.distance(p));
+                if *k <= 0.0 {
+                    return a.max(-b);
+                }
+                let h = (0.5 - 0.5 * (a + b) / k).clamp(0.0, 1.0);
+                mix(a, -b, h) + k * h * (1.0 - h)
+            }
+            Sdf::Repeat { period, sdf } => {
+                let wrap = |x: f64, period: f64| {
+                    if period == 0.0 {
+                        x
+                    } else {
+                        x - period * (x / period).round()
+                    }
+                };
+                sdf.distance(Vec3::new(
+                    wrap(p.x, period.x),
+                    wrap(p.y, period.y),
+                    wrap(p.z, period.z),
+                ))
+            }
+        }
+    }
+
+    /// Returns the surface normal at point `p`, estimated from the gradient of the distance
+    /// function with central differences of step size `h`.
+    pub fn normal_at(&self, p: Vec3, h: f64) -> Vec3 {
+        let dx = Vec3::new(h, 0.0, 0.0);
+        let dy = Vec3::new(0.0, h, 0.0);
+        let dz = Vec3::new(0.0, 0.0, h);
+        Vec3::new(
+            self.distance(p + dx) - self.distance(p - dx),
+            self.distance(p + dy) - self.distance(p - dy),
+            self.distance(p + dz) - self.distance(p - dz),
+        )
+        .normalized()
+    }
+}
+
+fn mix(a: f64, b: f64, h: f64) -> f64 {
+    a * (1.0 - h) + b * h
+}
+
+/// A shape defined by a signed distance function, intersected by sphere tracing.
+///
+/// Marching is limited to `bounds`, which must contain the shape (or, for a repeated shape, the
+/// part that should be visible). A hit is found where the distance drops below `epsilon`; rays
+/// give up after `max_steps` steps, so increase it if thin or grazing features go missing.
+#[derive(Debug, Clone)]
+pub struct SdfShape {
+    pub sdf: Sdf,
+    pub bounds: Aabb,
+    pub max_steps: usize,
+    pub epsilon: f64,
+}
+
+impl SdfShape {
+    /// Returns a shape for the distance function, with default values for `max_steps` and
+    /// `epsilon`.
+    pub fn new(sdf: Sdf, bounds: Aabb) -> SdfShape {
+        SdfShape {
+            sdf,
+            bounds,
+            max_steps: 256,
+            epsilon: 1E-4,
+        }
+    }
+
+    /// Return the values `t` where the ray intersects the shape, in a sorted vector.
+    ///
+    /// Starting where the line through the ray enters the bounds, this steps along the line by the
+    /// distance to the surface, which is always safe. At each point where it gets close to the
+    /// surface, it records a hit and continues on the other side. If marching reaches the end of the
+    /// bounds inside the shape, the end is recorded as a hit as well, so hits come in
+    /// entering/leaving pairs; if it runs out of steps inside the shape, the last entering hit is
+    /// dropped instead.
+    pub fn intersect_ray(&self, ray: Ray) -> Vec<f64> {
+        let (t_start, t_end) = match self.bounds.intersect_ray(ray) {
+            Some(ts) => ts,
+            None => return vec![],
+        };
+        let eps = self.epsilon;
+        let scale = ray.direction.len(); // distance per unit of t
+        let mut hits = vec![];
+        let mut t = t_start;
+        let mut inside = self.sdf.distance(ray.at(t)) < 0.0;
+        if inside {
+            hits.push(t);
+        }
+
+        // right after a hit, step slowly until the ray is clear of the surface
+        let mut leaving = false;
+        let mut steps = 0;
+        while t <= t_end && steps < self.max_steps {
+            steps += 1;
+            let d = self.sdf.distance(ray.at(t));
+            let to_surface = if inside { -d } else { d };
+            if leaving {
+                if to_surface <= -eps {
+                    // back on the side the ray came from: it only touched the surface
+                    hits.push(t);
+                    inside = !inside;
+                    leaving = false;
+                    continue;
+                }
+                if to_surface < eps {
+                    t += eps / scale;
+                    continue;
+                }
+                leaving = false;
+            }
+            if to_surface < eps {
+                hits.push(t);
+                inside = !inside;
+                leaving = true;
+                t += eps / scale;
+                continue;
+            }
+            t += to_surface / scale;
+        }
+        if inside {
+            if t > t_end {
+                hits.push(t_end);
+            } else {
+                // out of steps: the ray's way out of the shape is unknown, so drop the way in
+                hits.pop();
+            }
+        }
+        hits
+    }
+
+    /// Returns the surface normal at point `p`, which must be on the surface of the shape.
+    pub fn normal_at(&self, p: Vec3) -> Vec3 {
+        self.sdf.normal_at(p, self.epsilon)
+    }
+
+    /// Returns the texture coordinates at point `p`, which must be on the surface of the shape.
+    ///
+    /// The point is projected onto the side of `bounds` that the normal points to most, like for
+    /// the faces of a cuboid.
+    pub fn uv_at(&self, p: Vec3) -> (f64, f64) {
+        box_mapping(self.bounds, p, self.normal_at(p)).0
+    }
+
+    /// Returns the direction in which `u` increases at point `p`, which must be on the surface of
+    /// the shape.
+    pub fn tangent_at(&self, p: Vec3) -> Vec3 {
+        box_mapping(self.bounds, p, self.normal_at(p)).1
+    }
+
+    /// Returns a box that contains the shape.
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1E-3
+    }
+
+    #[test]
+    fn sdf_distance() {
+        let sphere = Sdf::Sphere { radius: 1.0 };
+        assert!(sphere.distance(Vec3::new(0.0, 3.0, 0.0)).approx_eq(2.0));
+        assert!(sphere.distance(Vec3::new(0.0, 0.0, 0.0)).approx_eq(-1.0));
+
+        let cube = Sdf::Box {
+            size: Vec3::new(2.0, 2.0, 2.0),
+        };
+        assert!(cube.distance(Vec3::new(3.0, 0.0, 0.0)).approx_eq(2.0));
+        assert!(cube
+            .distance(Vec3::new(2.0, 2.0, 1.0))
+            .approx_eq(2f64.sqrt()));
+        assert!(cube.distance(Vec3::new(0.5, 0.0, 0.0)).approx_eq(-0.5));
+
+        let torus = Sdf::Torus {
+            major_radius: 2.0,
+            minor_radius: 0.5,
+        };
+        assert!(torus.distance(Vec3::new(0.0, 0.0, 0.0)).approx_eq(1.5));
+        assert!(torus.distance(Vec3::new(0.0, 1.0, 2.0)).approx_eq(0.5));
+
+        let capsule = Sdf::Capsule {
+            a: Vec3::new(0.0, 0.0, 0.0),
+            b: Vec3::new(0.0, 2.0, 0.0),
+            radius: 0.5,
+        };
+        assert!(capsule.distance(Vec3::new(1.0, 1.0, 0.0)).approx_eq(0.5));
+        assert!(capsule.distance(Vec3::new(0.0, 4.0, 0.0)).approx_eq(1.5));
+
+        let moved = Sdf::Translate {
+            offset: Vec3::new(5.0, 0.0, 0.0),
+            sdf: Box::new(sphere.clone()),
+        };
+        assert!(moved.distance(Vec3::new(5.0, 2.0, 0.0)).approx_eq(1.0));
+
+        let repeated = Sdf::Repeat {
+            period: Vec3::new(4.0, 0.0, 0.0),
+            sdf: Box::new(sphere.clone()),
+        };
+        assert!(repeated.distance(Vec3::new(8.0, 0.0, 0.0)).approx_eq(-1.0));
+        assert!(repeated.distance(Vec3::new(-6.0, 0.0, 0.0)).approx_eq(1.0));
+        assert!(repeated.distance(Vec3::new(4.0, 3.0, 0.0)).approx_eq(2.0));
+    }
+
+    #[test]
+    fn sdf_smooth_combinations() {
+        let a = Box::new(Sdf::Sphere { radius: 1.0 });
+        let b = Box::new(Sdf::Translate {
+            offset: Vec3::new(1.5, 0.0, 0.0),
+            sdf: Box::new(Sdf::Sphere { radius: 1.0 }),
+        });
+        let p = Vec3::new(0.75, 0.0, 0.0);
+
+        // without smoothing, these are the usual min and max
+        let union = Sdf::SmoothUnion {
+            a: a.clone(),
+            b: b.clone(),
+            k: 0.0,
+        };
+        assert!(union.distance(p).approx_eq(-0.25));
+        let subtraction = Sdf::SmoothSubtraction {
+            a: a.clone(),
+            b: b.clone(),
+            k: 0.0,
+        };
+        assert!(subtraction.distance(p).approx_eq(0.25));
+
+        // smoothing fills in the seam, and takes away more around the cut
+        let union = Sdf::SmoothUnion {
+            a: a.clone(),
+            b: b.clone(),
+            k: 0.5,
+        };
+        assert!(union.distance(p) < -0.25);
+        let q = Vec3::new(0.75, 0.8, 0.0);
+        assert!(union.distance(q) < a.distance(q).min(b.distance(q)));
+        let subtraction = Sdf::SmoothSubtraction { a, b, k: 1.0 };
+        let r = Vec3::new(0.4, 0.0, 0.0);
+        assert!(subtraction.distance(r) > -0.1);
+    }
+
+    #[test]
+    fn sdf_normal_at() {
+        let cube = Sdf::Box {
+            size: Vec3::new(2.0, 2.0, 2.0),
+        };
+        let n = cube.normal_at(Vec3::new(1.0, 0.2, 0.3), 1E-4);
+        assert!(n.approx_eq(Vec3::new(1.0, 0.0, 0.0)));
+        let sphere = Sdf::Sphere { radius: 1.0 };
+        let p = Vec3::new(0.6, 0.0, 0.8);
+        assert!(sphere.normal_at(p, 1E-4).approx_eq(p));
+    }
+
+    #[test]
+    fn sdf_shape_intersect_ray() {
+        let sphere = SdfShape::new(
+            Sdf::Sphere { radius: 1.0 },
+            Aabb {
+                min: Vec3::new(-1.0, -1.0, -1.0),
+                max: Vec3::new(1.0, 1.0, 1.0),
+            },
+        );
+        let ray = Ray {
+            origin: Vec3::new(0.0, 0.0, -5.0),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let ts = sphere.intersect_ray(ray);
+        assert_eq!(ts.len(), 2, "got {:?}", ts);
+        assert!(close(ts[0], 4.0) && close(ts[1], 6.0), "got {:?}", ts);
+
+        // the values of t don't depend on the length of the direction
+        let ray = Ray {
+            origin: Vec3::new(0.0, 0.5, -5.0),
+            direction: Vec3::new(0.0, 0.0, 2.0),
+            time: 0.0,
+        };
+        let ts = sphere.intersect_ray(ray);
+        let d = 0.75f64.sqrt();
+        assert_eq!(ts.len(), 2, "got {:?}", ts);
+        assert!(close(ts[0], (5.0 - d) / 2.0), "got {:?}", ts);
+        assert!(close(ts[1], (5.0 + d) / 2.0), "got {:?}", ts);
+
+        // missing the sphere, but not the bounds
+        let ray = Ray {
+            origin: Vec3::new(0.9, 0.9, -5.0),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        assert!(sphere.intersect_ray(ray).is_empty());
+
+        // a row of spheres, cut off by the bounds in the middle of the last one
+        let row = SdfShape::new(
+            Sdf::Repeat {
+                period: Vec3::new(3.0, 0.0, 0.0),
+                sdf: Box::new(Sdf::Sphere { radius: 1.0 }),
+            },
+            Aabb {
+                min: Vec3::new(-1.0, -1.0, -1.0),
+                max: Vec3::new(6.0, 1.0, 1.0),
+            },
+        );
+        let ray = Ray {
+            origin: Vec3::new(-5.0, 0.0, 0.0),
+            direction: Vec3::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let ts = row.intersect_ray(ray);
+        let want = [4.0, 6.0, 7.0, 9.0, 10.0, 11.0];
+        assert_eq!(ts.len(), want.len(), "got {:?}", ts);
+        for (got, want) in ts.iter().zip(want.iter()) {
+            assert!(close(*got, *want), "got {:?}", ts);
+        }
+
+        // a grazing ray takes many small steps; running out of steps inside the sphere doesn't make
+        // up a hit where the ray leaves it
+        let ray = Ray {
+            origin: Vec3::new(0.0, 0.99, -5.0),
+            direction: Vec3::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let d = (1.0 - 0.99f64.powi(2)).sqrt();
+        let mut sphere = sphere;
+        let mut found = false;
+        for max_steps in 1..200 {
+            sphere.max_steps = max_steps;
+            let ts = sphere.intersect_ray(ray);
+            if !ts.is_empty() {
+                assert_eq!(ts.len(), 2, "got {:?}", ts);
+                // at grazing angles, being within epsilon of the surface is a long way along the ray
+                let near = |t: f64, want: f64| (t - want).abs() < 0.01;
+                assert!(near(ts[0], 5.0 - d) && near(ts[1], 5.0 + d), "got {:?}", ts);
+                found = true;
+            }
+        }
+        assert!(found);
+    }
+}
@@ -0,0 +1,349 @@
+//! Defines textures, which give the color of a surface at each point.
+
+use crate::color::Color;
+use crate::image::Image;
+use crate::pattern::{ColorRamp, Pattern};
+use crate::vec3::Vec3;
+use std::fs;
+use std::io;
+use std::rc::Rc;
+
+/// A source of colors for a surface.
+///
+/// Image textures are looked up by texture coordinates `(u, v)`: each shape maps its surface to
+/// texture coordinates between 0 and 1, e.g. longitude and latitude for spheres. Solid textures
+/// are computed from the point in the object's coordinate system instead.
+#[derive(Debug, Clone)]
+pub enum Texture {
+    /// An image stretched over the texture coordinates, with `(0, 0)` at the bottom left corner and
+    /// `(1, 1)` at the top right. `address` defines what happens outside that range.
+    Image {
+        image: Rc<Image>,
+        filter: Filter,
+        address: AddressMode,
+    },
+
+    /// A pattern in 3D space, so it runs through the object like the grain through a block of
+    /// wood. The pattern is scaled up by `scale`, and its values are mapped to colors by `colors`.
+    Solid {
+        pattern: Pattern,
+        scale: f64,
+        colors: ColorRamp,
+    },
+}
+
+/// How colors are looked up between the pixels of an image texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    /// Use the color of the closest pixel, which makes pixels visible as squares up close.
+    Nearest,
+
+    /// Interpolate between the four closest pixels.
+    Bilinear,
+}
+
+/// How texture coordinates outside the range 0 to 1 are mapped onto an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressMode {
+    /// Repeat the image, e.g. for tiles.
+    Wrap,
+
+    /// Repeat the pixels at the edges of the image.
+    Clamp,
+
+    /// Repeat the image, flipping every other copy so the edges match up.
+    Mirror,
+}
+
+impl AddressMode {
+    /// Maps a pixel index, which may be outside the image, to a pixel in an image of size `n`,
+    /// which is never 0 (see `Image::new`).
+    fn apply(self, i: i64, n: usize) -> usize {
+        let n = n as i64;
+        let i = match self {
+            AddressMode::Wrap => i.rem_euclid(n),
+            AddressMode::Clamp => i.clamp(0, n - 1),
+            AddressMode::Mirror => {
+                let m = i.rem_euclid(2 * n);
+                if m < n {
+                    m
+                } else {
+                    2 * n - 1 - m
+                }
+            }
+        };
+        i as usize
+    }
+}
+
+impl Texture {
+    /// Loads an image texture from a PPM file, with bilinear filtering, wrapped at the edges.
+    ///
+    /// Returns an error if the file can't be read or isn't a valid, non-empty image.
+    pub fn load_ppm(path: &str) -> io::Result<Texture> {
+        Texture::read_ppm(&fs::read(path)?)
+    }
+
+    /// Parses an image texture in PPM format, like `load_ppm`.
+    pub fn read_ppm(data: &[u8]) -> io::Result<Texture> {
+        Ok(Texture::Image {
+            image: Rc::new(Image::read_ppm(data)?),
+            filter: Filter::Bilinear,
+            address: AddressMode::Wrap,
+        })
+    }
+
+    /// Returns the color at the given texture coordinates and point in object coordinates.
+    pub fn color_at(&self, uv: (f64, f64), p: Vec3) -> Color {
+        match self {
+            Texture::Image {
+                image,
+                filter,
+                address,
+            } => {
+                let (w, h) = (image.width(), image.height());
+                // pixel coordinates, with row 0 at the top of the image
+                let x = uv.0 * w as f64;
+                let y = (1.0 - uv.1) * h as f64;
+                let pixel = |x: i64, y: i64| image.pixel(address.apply(x, w), address.apply(y, h));
+                match filter {
+                    Filter::Nearest => pixel(x.floor() as i64, y.floor() as i64),
+                    Filter::Bilinear => {
+                        // the centers of the pixels are at half-integer coordinates
+                        let (x, y) = (x - 0.5, y - 0.5);
+                        let (x0, y0) = (x.floor(), y.floor());
+                        let (s, t) = (x - x0, y - y0);
+                        let (x0, y0) = (x0 as i64, y0 as i64);
+                        let top = (1.0 - s) * pixel(x0, y0) + s * pixel(x0 + 1, y0);
+                        let bottom = (1.0 - s) * pixel(x0, y0 + 1) + s * pixel(x0 + 1, y0 + 1);
+                        (1.0 - t) * top + t * bottom
+                    }
+                }
+            }
+            Texture::Solid {
+                pattern,
+                scale,
+                colors,
+            } => colors.color_at(pattern.value_at(p / *scale)),
+        }
+    }
+}
+
+/// A texture that perturbs the normals of a surface, to add detail like bumps or scratches that
+/// would be too small to model as geometry.
+///
+/// Both kinds are looked up by texture coordinates and use the tangent frame of the surface, so
+/// solid textures don't work well as bump maps.
+#[derive(Debug, Clone)]
+pub enum BumpMap {
+    /// A tangent-space normal map: the red, green, and blue channels give the normal's components
+    /// along the tangent, the bitangent, and the surface normal, mapped from -1..1 to 0..1. A flat
+    /// surface is the color `(0.5, 0.5, 1.0)`.
+    Normal(Texture),
+
+    /// A height map, where brighter colors are higher. The normal tilts away from the slope of the
+    /// heights; `strength` scales the effect.
+    Height { texture: Texture, strength: f64 },
+}
+
+impl BumpMap {
+    /// Returns the perturbed normal, with length 1, for a surface with normal `n` and tangent
+    /// `tangent` (see `Hit::tangent`) at texture coordinates `uv` and point `p` in object
+    /// coordinates.
+    pub fn normal_at(&self, n: Vec3, tangent: Vec3, uv: (f64, f64), p: Vec3) -> Vec3 {
+        let (t, b, n) = tangent_frame(n, tangent);
+        match self {
+            BumpMap::Normal(texture) => {
+                let Color(x, y, z) = texture.color_at(uv, p);
+                let m = (2.0 * x - 1.0, 2.0 * y - 1.0, 2.0 * z - 1.0);
+                (m.0 * t + m.1 * b + m.2 * n).normalized()
+            }
+            BumpMap::Height { texture, strength } => {
+                let height = |u: f64, v: f64| {
+                    let Color(r, g, b) = texture.color_at((u, v), p);
+                    (r + g + b) / 3.0
+                };
+                // the slope of the height map, by central differences
+                let d = 1E-3;
+                let (u, v) = uv;
+                let dh_du = (height(u + d, v) - height(u - d, v)) / (2.0 * d);
+                let dh_dv = (height(u, v + d) - height(u, v - d)) / (2.0 * d);
+                (n - *strength * (dh_du * t + dh_dv * b)).normalized()
+            }
+        }
+    }
+}
+
+/// Returns an orthonormal frame (tangent, bitangent, normal) for normal `n`, with the tangent as
+/// close as possible to `tangent`. If `tangent` is zero or parallel to `n`, any perpendicular
+/// direction is used instead.
+fn tangent_frame(n: Vec3, tangent: Vec3) -> (Vec3, Vec3, Vec3) {
+    let n = n.normalized();
+    let mut t = tangent - tangent.dot(n) * n;
+    if t.len() < 1E-9 {
+        let axis = if n.x.abs() < 0.9 {
+            Vec3::new(1.0, 0.0, 0.0)
+        } else {
+            Vec3::new(0.0, 1.0, 0.0)
+        };
+        t = axis - axis.dot(n) * n;
+    }
+    let t = t.normalized();
+    (t, t.cross(n), n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+
+    const RED: Color = Color(1.0, 0.0, 0.0);
+    const GREEN: Color = Color(0.0, 1.0, 0.0);
+    const BLUE: Color = Color(0.0, 0.0, 1.0);
+
+    // red and green on top, blue and white at the bottom
+    fn image(filter: Filter, address: AddressMode) -> Texture {
+        let image = Image::new(2, 2, vec![RED, GREEN, BLUE, Color::WHITE]);
+        Texture::Image {
+            image: Rc::new(image),
+            filter,
+            address,
+        }
+    }
+
+    // looks up an image texture, which doesn't depend on the point
+    fn at(texture: &Texture, u: f64, v: f64) -> Color {
+        texture.color_at((u, v), Vec3::new(0.0, 0.0, 0.0))
+    }
+
+    #[test]
+    fn image_texture_nearest() {
+        let texture = image(Filter::Nearest, AddressMode::Wrap);
+        assert!(at(&texture, 0.25, 0.75).approx_eq(RED));
+        assert!(at(&texture, 0.75, 0.75).approx_eq(GREEN));
+        assert!(at(&texture, 0.1, 0.1).approx_eq(BLUE));
+        assert!(at(&texture, 0.9, 0.4).approx_eq(Color::WHITE));
+    }
+
+    #[test]
+    fn image_texture_bilinear() {
+        let texture = image(Filter::Bilinear, AddressMode::Clamp);
+        // exact colors at the centers of the pixels
+        assert!(at(&texture, 0.25, 0.75).approx_eq(RED));
+        assert!(at(&texture, 0.75, 0.25).approx_eq(Color::WHITE));
+        // halfway between two pixels
+        assert!(at(&texture, 0.5, 0.75).approx_eq(Color(0.5, 0.5, 0.0)));
+        assert!(at(&texture, 0.25, 0.5).approx_eq(Color(0.5, 0.0, 0.5)));
+        // the average of all four in the middle
+        assert!(at(&texture, 0.5, 0.5).approx_eq(Color(0.5, 0.5, 0.5)));
+        // clamped at the edges
+        assert!(at(&texture, 0.0, 0.75).approx_eq(RED));
+    }
+
+    #[test]
+    fn texture_read_ppm() {
+        let texture = Texture::read_ppm(b"P3 1 1 255\n0 51 255\n").unwrap();
+        assert!(at(&texture, 0.5, 0.5).approx_eq(Color(0.0, 0.2, 1.0)));
+
+        // empty images are rejected when loading, rather than when the texture is used
+        assert!(Texture::read_ppm(b"P3 0 0 255\n").is_err());
+    }
+
+    #[test]
+    fn image_texture_address_modes() {
+        let wrap = image(Filter::Nearest, AddressMode::Wrap);
+        assert!(at(&wrap, 1.1, 0.1).approx_eq(BLUE));
+        assert!(at(&wrap, -0.1, -0.1).approx_eq(GREEN));
+
+        let clamp = image(Filter::Nearest, AddressMode::Clamp);
+        assert!(at(&clamp, 1.1, 0.1).approx_eq(Color::WHITE));
+        assert!(at(&clamp, -5.0, 3.0).approx_eq(RED));
+
+        let mirror = image(Filter::Nearest, AddressMode::Mirror);
+        assert!(at(&mirror, 1.1, 0.1).approx_eq(Color::WHITE));
+        assert!(at(&mirror, 1.6, 0.1).approx_eq(BLUE));
+        assert!(at(&mirror, 2.1, 0.1).approx_eq(BLUE));
+        assert!(at(&mirror, -0.1, 0.9).approx_eq(RED));
+
+        // with bilinear filtering, wrapping blends across the edge
+        let wrap = image(Filter::Bilinear, AddressMode::Wrap);
+        assert!(at(&wrap, 0.0, 0.75).approx_eq(Color(0.5, 0.5, 0.0)));
+    }
+
+    #[test]
+    fn solid_texture() {
+        let texture = Texture::Solid {
+            pattern: Pattern::Checker,
+            scale: 0.5,
+            colors: ColorRamp::two_colors(RED, BLUE),
+        };
+        let uv = (0.0, 0.0);
+        assert!(texture
+            .color_at(uv, Vec3::new(0.25, 0.25, 0.25))
+            .approx_eq(RED));
+        assert!(texture
+            .color_at(uv, Vec3::new(0.75, 0.25, 0.25))
+            .approx_eq(BLUE));
+        assert!(texture
+            .color_at(uv, Vec3::new(0.75, -0.25, 0.25))
+            .approx_eq(RED));
+    }
+
+    #[test]
+    fn normal_map() {
+        let n = Vec3::new(0.0, 0.0, -2.0);
+        let tangent = Vec3::new(3.0, 0.0, 0.0);
+        let uv = (0.5, 0.5);
+        let p = Vec3::new(0.0, 0.0, 0.0);
+        let bitangent = Vec3::new(0.0, 1.0, 0.0);
+
+        let normal_map = |c: Color| {
+            let image = Image::new(1, 1, vec![c]);
+            BumpMap::Normal(Texture::Image {
+                image: Rc::new(image),
+                filter: Filter::Nearest,
+                address: AddressMode::Wrap,
+            })
+        };
+        let flat = normal_map(Color(0.5, 0.5, 1.0));
+        assert!(flat
+            .normal_at(n, tangent, uv, p)
+            .approx_eq(Vec3::new(0.0, 0.0, -1.0)));
+        let tilted = normal_map(Color(1.0, 0.5, 0.5));
+        assert!(tilted
+            .normal_at(n, tangent, uv, p)
+            .approx_eq(Vec3::new(1.0, 0.0, 0.0)));
+        let tilted = normal_map(Color(0.5, 1.0, 1.0));
+        let want = (bitangent + Vec3::new(0.0, 0.0, -1.0)).normalized();
+        assert!(tilted.normal_at(n, tangent, uv, p).approx_eq(want));
+
+        // without a tangent, any frame will do, but a flat map still gives the normal
+        let zero = Vec3::new(0.0, 0.0, 0.0);
+        assert!(flat
+            .normal_at(n, zero, uv, p)
+            .approx_eq(Vec3::new(0.0, 0.0, -1.0)));
+    }
+
+    #[test]
+    fn height_map() {
+        let n = Vec3::new(0.0, 1.0, 0.0);
+        let tangent = Vec3::new(1.0, 0.0, 0.0);
+        let p = Vec3::new(0.0, 0.0, 0.0);
+        // a ramp along u: black on the left, white on the right
+        let image = Image::new(2, 1, vec![Color::BLACK, Color::WHITE]);
+        let bump = BumpMap::Height {
+            texture: Texture::Image {
+                image: Rc::new(image),
+                filter: Filter::Bilinear,
+                address: AddressMode::Clamp,
+            },
+            strength: 0.5,
+        };
+        // in the middle, the height goes up by 2 per unit of u, so the normal tilts towards -u
+        let got = bump.normal_at(n, tangent, (0.5, 0.5), p);
+        assert!(got.approx_eq(Vec3::new(-1.0, 1.0, 0.0).normalized()));
+        // flat where the height is clamped
+        let got = bump.normal_at(n, tangent, (0.1, 0.5), p);
+        assert!(got.approx_eq(n));
+    }
+}
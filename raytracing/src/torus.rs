@@ -0,0 +1,168 @@
+//! Defines a torus primitive.
+
+use crate::aabb::Aabb;
+use crate::ray::Ray;
+use crate::scene::solve_quartic;
+use crate::vec3::Vec3;
+use std::f64::consts::PI;
+
+/// A torus around the y axis, centered on the origin.
+///
+/// The torus is the surface swept by a circle with radius `minor_radius` whose center moves along
+/// a circle with radius `major_radius` in the plane `y = 0`.
+#[derive(Debug, Clone, Copy)]
+pub struct Torus {
+    pub major_radius: f64,
+    pub minor_radius: f64,
+}
+
+impl Torus {
+    /// Return the values `t` where the ray intersects the torus.
+    ///
+    /// Returns 0 to 4 values in a sorted vector.
+    pub fn intersect_ray(&self, ray: Ray) -> Vec<f64> {
+        // Move the origin to the point on the line closest to the center of the torus, so the
+        // coefficients don't get large for rays that start far away.
+        let d = ray.direction;
+        let t_offset = -ray.origin.dot(d) / d.dot(d);
+        let o = ray.at(t_offset);
+
+        // insert the ray into (x^2 + y^2 + z^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + z^2)
+        let r2 = self.major_radius * self.major_radius;
+        let dd = d.dot(d);
+        let od = o.dot(d);
+        let k = o.dot(o) + r2 - self.minor_radius * self.minor_radius;
+        let a = dd * dd;
+        let b = 4.0 * dd * od;
+        let c = 2.0 * dd * k + 4.0 * od * od - 4.0 * r2 * (d.x * d.x + d.z * d.z);
+        let d1 = 4.0 * od * k - 8.0 * r2 * (o.x * d.x + o.z * d.z);
+        let e = k * k - 4.0 * r2 * (o.x * o.x + o.z * o.z);
+
+        solve_quartic(a, b, c, d1, e)
+            .into_iter()
+            .map(|t| t + t_offset)
+            .collect()
+    }
+
+    /// Returns the surface normal at point `p`, which must be on the surface of the torus.
+    pub fn normal_at(&self, p: Vec3) -> Vec3 {
+        // the gradient of the implicit equation, divided by 4
+        let r2 = self.major_radius * self.major_radius;
+        let s = p.dot(p) - self.minor_radius * self.minor_radius;
+        Vec3::new(p.x * (s - r2), p.y * (s + r2), p.z * (s - r2))
+    }
+
+    /// Returns the texture coordinates at point `p`, which must be on the surface of the torus.
+    ///
+    /// `u` goes around the y axis, starting at the back, and `v` goes around the tube, starting on
+    /// the outside.
+    pub fn uv_at(&self, p: Vec3) -> (f64, f64) {
+        let u = 0.5 + p.x.atan2(-p.z) / (2.0 * PI);
+        let from_ring = (p.x * p.x + p.z * p.z).sqrt() - self.major_radius;
+        let v = 0.5 + p.y.atan2(-from_ring) / (2.0 * PI);
+        (u, v)
+    }
+
+    /// Returns the direction in which `u` increases at point `p`, which must be on the surface of
+    /// the torus.
+    pub fn tangent_at(&self, p: Vec3) -> Vec3 {
+        Vec3::new(-p.z, 0.0, p.x)
+    }
+
+    /// Returns a box that contains the torus.
+    pub fn bounds(&self) -> Aabb {
+        let outer = self.major_radius + self.minor_radius;
+        let r = self.minor_radius;
+        Aabb {
+            min: Vec3::new(-outer, -r, -outer),
+            max: Vec3::new(outer, r, outer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+
+    fn torus() -> Torus {
+        Torus {
+            major_radius: 2.0,
+            minor_radius: 0.5,
+        }
+    }
+
+    #[test]
+    fn torus_intersect_ray() {
+        // through the middle, crossing the ring twice
+        let ray = Ray {
+            origin: Vec3::new(-5.0, 0.0, 0.0),
+            direction: Vec3::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let want = vec![2.5, 3.5, 6.5, 7.5];
+        let got = torus().intersect_ray(ray);
+        assert!(got.approx_eq(&want), "got {:?}", got);
+
+        // from above, through the ring
+        let ray = Ray {
+            origin: Vec3::new(0.0, 3.0, 2.0),
+            direction: Vec3::new(0.0, -2.0, 0.0),
+            time: 0.0,
+        };
+        let got = torus().intersect_ray(ray);
+        assert!(got.approx_eq(&vec![1.25, 1.75]), "got {:?}", got);
+
+        // from above, through the hole
+        let ray = Ray {
+            origin: Vec3::new(0.0, 3.0, 0.0),
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        assert!(torus().intersect_ray(ray).is_empty());
+
+        // from far away, at an angle
+        let ray = Ray {
+            origin: Vec3::new(-1000.0, 0.0, -1000.0),
+            direction: Vec3::new(1.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let got = torus().intersect_ray(ray);
+        let s = 0.5f64.sqrt();
+        let want: Vec<f64> = [-2.5, -1.5, 1.5, 2.5]
+            .iter()
+            .map(|x| 1000.0 + x * s)
+            .collect();
+        assert_eq!(got.len(), 4, "got {:?}", got);
+        for (got, want) in got.iter().zip(want.iter()) {
+            assert!((got - want).abs() < 1e-9, "got {}, want {}", got, want);
+        }
+    }
+
+    #[test]
+    fn torus_normal_at() {
+        let cases = [
+            (Vec3::new(2.5, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)),
+            (Vec3::new(1.5, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0)),
+            (Vec3::new(0.0, 0.5, 2.0), Vec3::new(0.0, 1.0, 0.0)),
+            (Vec3::new(0.0, -0.5, -2.0), Vec3::new(0.0, -1.0, 0.0)),
+        ];
+        for (p, want) in cases {
+            let got = torus().normal_at(p).normalized();
+            assert!(got.approx_eq(want), "normal_at({:?}) == {:?}", p, got);
+        }
+    }
+
+    #[test]
+    fn torus_uv_at() {
+        let cases = [
+            (Vec3::new(0.0, 0.0, -1.5), (0.5, 0.5)),
+            (Vec3::new(2.0, 0.5, 0.0), (0.75, 0.75)),
+            (Vec3::new(-2.0, -0.5, 0.0), (0.25, 0.25)),
+        ];
+        for (p, want) in cases {
+            let got = torus().uv_at(p);
+            assert!(got.approx_eq(want), "uv_at({:?}) == {:?}", p, got);
+        }
+    }
+}
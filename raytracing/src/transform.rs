@@ -0,0 +1,188 @@
+//! Defines a type for affine transformations that keeps track of its inverse.
+
+use crate::approx::ApproxEq;
+use crate::matrix::Mat4;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use std::ops::Mul;
+
+/// An invertible affine transformation.
+///
+/// The inverse is computed once when the transformation is created, since it's needed every time
+/// a ray is transformed into an object's local coordinate system.
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    matrix: Mat4,
+    inverse: Mat4,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        matrix: Mat4::IDENTITY,
+        inverse: Mat4::IDENTITY,
+    };
+
+    /// Returns a transformation for the given matrix, or `None` if the matrix isn't invertible.
+    pub fn new(matrix: Mat4) -> Option<Transform> {
+        let inverse = matrix.inverse()?;
+        Some(Transform { matrix, inverse })
+    }
+
+    /// Returns a transformation that moves points by `offset`.
+    pub fn translation(offset: Vec3) -> Transform {
+        Transform {
+            matrix: Mat4::translation(offset),
+            inverse: Mat4::translation(-offset),
+        }
+    }
+
+    /// Returns a transformation that rotates by `angle` radians around `axis`.
+    pub fn rotation(axis: Vec3, angle: f64) -> Transform {
+        Transform {
+            matrix: Mat4::rotation(axis, angle),
+            inverse: Mat4::rotation(axis, -angle),
+        }
+    }
+
+    /// Returns a transformation that scales by the given factor along each axis, or `None` if any
+    /// of the factors is zero, since the transformation isn't invertible then.
+    pub fn scaling(factors: Vec3) -> Option<Transform> {
+        if factors.x == 0.0 || factors.y == 0.0 || factors.z == 0.0 {
+            return None;
+        }
+        Some(Transform {
+            matrix: Mat4::scaling(factors),
+            inverse: Mat4::scaling(Vec3::new(1.0 / factors.x, 1.0 / factors.y, 1.0 / factors.z)),
+        })
+    }
+
+    /// Returns the transformation matrix.
+    pub fn matrix(&self) -> Mat4 {
+        self.matrix
+    }
+
+    /// Returns the inverse transformation.
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    /// Transforms a point.
+    pub fn point(&self, p: Vec3) -> Vec3 {
+        self.matrix.transform_point(p)
+    }
+
+    /// Transforms a vector, e.g. a direction.
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        self.matrix.transform_vector(v)
+    }
+
+    /// Transforms a surface normal and normalizes the result.
+    ///
+    /// Normals have to be transformed with the inverse transpose of the matrix to stay
+    /// perpendicular to the surface under non-uniform scaling.
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        (self.inverse.linear().transpose() * n).normalized()
+    }
+
+    /// Transforms a ray.
+    ///
+    /// The direction is not normalized, so a point at `t` along the transformed ray is the
+    /// transformed point at `t` along the original ray.
+    pub fn ray(&self, ray: Ray) -> Ray {
+        Ray {
+            origin: self.point(ray.origin),
+            direction: self.vector(ray.direction),
+            time: ray.time,
+        }
+    }
+
+    /// Applies the inverse transformation to a point.
+    pub fn inverse_point(&self, p: Vec3) -> Vec3 {
+        self.inverse.transform_point(p)
+    }
+
+    /// Applies the inverse transformation to a ray (see [`Transform::ray`]).
+    pub fn inverse_ray(&self, ray: Ray) -> Ray {
+        self.inverse().ray(ray)
+    }
+}
+
+impl ApproxEq for Transform {
+    fn approx_eq(self, other: Transform) -> bool {
+        self.matrix.approx_eq(other.matrix)
+    }
+}
+
+/// Composes two transformations; `a * b` applies `b` first, then `a`.
+impl Mul<Transform> for Transform {
+    type Output = Transform;
+
+    fn mul(self, other: Transform) -> Transform {
+        Transform {
+            matrix: self.matrix * other.matrix,
+            inverse: other.inverse * self.inverse,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_2;
+
+    #[test]
+    fn transform_new() {
+        let m = Mat4::translation(Vec3::new(1.0, 2.0, 3.0));
+        let t = Transform::new(m).unwrap();
+        assert!(t
+            .inverse
+            .approx_eq(Mat4::translation(Vec3::new(-1.0, -2.0, -3.0))));
+        assert!(Transform::new(Mat4::scaling(Vec3::new(0.0, 1.0, 1.0))).is_none());
+        assert!(Transform::scaling(Vec3::new(1.0, 0.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn transform_compose() {
+        let t = Transform::translation(Vec3::new(1.0, 0.0, 0.0))
+            * Transform::rotation(Vec3::new(0.0, 0.0, 1.0), FRAC_PI_2)
+            * Transform::scaling(Vec3::new(2.0, 1.0, 1.0)).unwrap();
+        let p = Vec3::new(1.0, 1.0, 1.0);
+        let q = t.point(p);
+        assert!(q.approx_eq(Vec3::new(0.0, 2.0, 1.0)));
+        assert!(t.inverse_point(q).approx_eq(p));
+        assert!((t * t.inverse()).approx_eq(Transform::IDENTITY));
+        assert!(t.vector(p).approx_eq(Vec3::new(-1.0, 2.0, 1.0)));
+    }
+
+    #[test]
+    fn transform_normal() {
+        // a plane with normal (1, 1, 0), stretched along x
+        let t = Transform::scaling(Vec3::new(2.0, 1.0, 1.0)).unwrap();
+        let n = Vec3::new(1.0, 1.0, 0.0);
+        let tangent = Vec3::new(1.0, -1.0, 0.0);
+        let transformed = t.normal(n);
+        assert!(transformed.dot(t.vector(tangent)).approx_eq(0.0));
+        assert!(transformed.approx_eq(Vec3::new(1.0, 2.0, 0.0).normalized()));
+    }
+
+    #[test]
+    fn transform_ray() {
+        let t = Transform::translation(Vec3::new(0.0, 0.0, 5.0))
+            * Transform::scaling(Vec3::new(2.0, 2.0, 2.0)).unwrap();
+        let ray = Ray {
+            origin: Vec3::new(0.0, 0.0, 1.0),
+            direction: Vec3::new(0.0, 1.0, 0.0),
+            time: 0.0,
+        };
+        let transformed = t.ray(ray);
+        assert!(transformed.origin.approx_eq(Vec3::new(0.0, 0.0, 7.0)));
+        assert!(transformed.direction.approx_eq(Vec3::new(0.0, 2.0, 0.0)));
+        assert!(transformed.at(1.5).approx_eq(t.point(ray.at(1.5))));
+        let back = t.inverse_ray(transformed);
+        assert!(back.origin.approx_eq(ray.origin));
+        assert!(back.direction.approx_eq(ray.direction));
+    }
+}
@@ -22,6 +22,15 @@ impl Vec3 {
         self.x * other.x + self.y * other.y + self.z * other.z
     }
 
+    /// Returns the cross product of the two vectors.
+    pub fn cross(&self, other: Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
     /// Returns the length (Euclidian norm) of the vector.
     pub fn len(&self) -> f64 {
         self.dot(*self).sqrt()
@@ -105,6 +114,17 @@ mod tests {
         assert!(a.dot(b).approx_eq(-1.5));
     }
 
+    #[test]
+    fn vec3_cross() {
+        let x = Vec3::new(1.0, 0.0, 0.0);
+        let y = Vec3::new(0.0, 1.0, 0.0);
+        assert!(x.cross(y).approx_eq(Vec3::new(0.0, 0.0, 1.0)));
+        assert!(y.cross(x).approx_eq(Vec3::new(0.0, 0.0, -1.0)));
+        let a = Vec3::new(0.5, 2.0, -0.5);
+        let b = Vec3::new(2.0, -0.5, 3.0);
+        assert!(a.cross(b).approx_eq(Vec3::new(5.75, -2.5, -4.25)));
+    }
+
     #[test]
     fn vec3_ops() {
         let a = Vec3::new(0.1, 0.2, 0.3);